@@ -0,0 +1,606 @@
+//! Generates the companion types that are defined alongside a component group
+
+use syn::{Ident, Generics, TypeGenerics, Visibility, parse_quote, ext::IdentExt};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::component_field::ComponentField;
+use crate::group_attrs::GroupAttrs;
+use crate::internal_ident;
+
+/// The lifetime of the world borrowed by the companion types that hold storages
+fn world_lifetime() -> syn::Lifetime {
+    parse_quote!('__world)
+}
+
+/// Returns a copy of the given generics with the world lifetime added as the first parameter
+fn with_world_lifetime(generics: &Generics) -> Generics {
+    let mut generics = generics.clone();
+    let lifetime = world_lifetime();
+    generics.params.insert(0, parse_quote!(#lifetime));
+    generics
+}
+
+/// Returns the name of the private field that the companion structs use to hold a marker of the
+/// group type
+///
+/// Field names are not hygienic, so underscores are prepended until the name is different from
+/// the name of every field in the group.
+fn marker_ident(fields: &[ComponentField]) -> Ident {
+    let mut name = "__group".to_string();
+    while fields.iter().any(|field| field.ident.unraw() == name) {
+        name.insert(0, '_');
+    }
+    internal_ident(&name)
+}
+
+/// Generates a struct that holds a read storage for every field and an inherent `read_world`
+/// method on the group to create it
+pub fn read_struct(
+    ident: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    group_attrs: &GroupAttrs,
+    fields: &[ComponentField],
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let read_ident = group_attrs.companion_ident(ident, "Read");
+    let lifetime = world_lifetime();
+    let read_generics = with_world_lifetime(generics);
+    let (read_impl_generics, read_ty_generics, read_where_clause) = read_generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
+    let tys: Vec<_> = fields.iter().map(|f| f.component_ty()).collect();
+    let marker = marker_ident(fields);
+    let doc = format!("Borrows the storage of every component in [`{}`] so they can be read \
+        for any number of entities without cloning", ident);
+    let read_world_doc = format!("Fetches the storages of this group from the world into a `{}`",
+        read_ident);
+
+    quote! {
+        #[doc = #doc]
+        #[allow(dead_code)]
+        #vis struct #read_ident #read_generics #read_where_clause {
+            #( #field_names : ::specs::ReadStorage<#lifetime, #tys> , )*
+            // Uses every type parameter of the group, even those that only appear in skipped fields
+            #marker: std::marker::PhantomData<fn() -> #ident #ty_generics>,
+        }
+
+        #[allow(dead_code)]
+        impl #read_impl_generics #read_ident #read_ty_generics #read_where_clause {
+            #(
+                #vis fn #field_names(&self, entity: ::specs::Entity) -> Option<&#tys> {
+                    self.#field_names.get(entity)
+                }
+            )*
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #[doc = #read_world_doc]
+            #vis fn read_world<#lifetime>(world: &#lifetime #krate::World) -> #read_ident #read_ty_generics {
+                let ( #(#field_names),* ) = world.system_data::<( #(::specs::ReadStorage<#lifetime, #tys>),* )>();
+                #read_ident {
+                    #( #field_names , )*
+                    #marker: std::marker::PhantomData,
+                }
+            }
+        }
+    }
+}
+
+/// Generates a struct with a flag for every field that is used to select a subset of the fields
+pub fn fields_struct(
+    ident: &Ident,
+    vis: &Visibility,
+    group_attrs: &GroupAttrs,
+    fields: &[ComponentField],
+) -> TokenStream {
+    let fields_ident = group_attrs.companion_ident(ident, "Fields");
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
+    let doc = format!("Selects a subset of the fields of [`{}`]. A field is selected if its flag \
+        is true. The default value selects no fields.", ident);
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        #[allow(dead_code)]
+        #vis struct #fields_ident {
+            #( #vis #field_names : bool , )*
+        }
+
+        #[allow(dead_code)]
+        impl #fields_ident {
+            /// Selects every field
+            #vis fn all() -> Self {
+                Self {
+                    #( #field_names : true ),*
+                }
+            }
+        }
+    }
+}
+
+/// Generates a struct that holds a `ReaderId` for the storage of each field, as well as an inherent
+/// `create_with_readers` method on the group that returns it
+pub fn readers_struct(
+    ident: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    group_attrs: &GroupAttrs,
+    fields: &[ComponentField],
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let readers_ident = group_attrs.companion_ident(ident, "Readers");
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
+    let tys: Vec<_> = fields.iter().map(|f| f.component_ty()).collect();
+    let tracked_bounds = tys.iter()
+        .map(|ty| crate::deferred_bound(quote! {<#ty as ::specs::Component>::Storage}, quote! {::specs::storage::Tracked}));
+    let entity = internal_ident("__entity");
+
+    let doc = format!("A reader of the storage events of each field of [`{}`], returned by \
+        `create_with_readers`", ident);
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        #vis struct #readers_ident {
+            #( #vis #field_names : ::specs::shrev::ReaderId<::specs::storage::ComponentEvent> , )*
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Same as `ComponentGroup::create`, but also registers a reader with the storage of
+            /// every field. The readers are registered after the components are inserted, so they
+            /// only receive the events of later changes.
+            ///
+            /// Can only be called if the storage of every component is tracked (e.g. a
+            /// `FlaggedStorage`).
+            #vis fn create_with_readers(self, world: &mut #krate::World) -> (::specs::Entity, #readers_ident)
+                where #( #tracked_bounds ),*
+            {
+                let #entity = <Self as #krate::ComponentGroup>::create(self, world);
+                let ( #(mut #field_names),* ) = world.system_data::<( #( ::specs::WriteStorage<#tys> ),* )>();
+                (#entity, #readers_ident {
+                    #( #field_names : #field_names.register_reader() , )*
+                })
+            }
+        }
+    }
+}
+
+/// Generates a struct that holds a column (`Vec`) for each field and the entities of each row, as
+/// well as an inherent `columns_from_world` method on the group to create it
+///
+/// If `has_skipped` is true, the group may have type parameters that are only used by its skipped
+/// fields, so a private marker field is added that uses every type parameter of the group. The
+/// columns can then only be created outside of the module with the generated `new` method.
+pub fn columns_struct(
+    ident: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    group_attrs: &GroupAttrs,
+    fields: &[ComponentField],
+    has_skipped: bool,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let columns_ident = group_attrs.companion_ident(ident, "Columns");
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
+    let column_tys: Vec<_> = fields.iter().map(|&ComponentField {ty, is_optional, ..}| {
+        if is_optional {
+            quote! {Vec<Option<#ty>>}
+        } else {
+            quote! {Vec<#ty>}
+        }
+    }).collect();
+    let tys = fields.iter().map(|f| f.component_ty());
+    let joinables = fields.iter().map(|&ComponentField {ident: field_name, is_optional, ..}| {
+        if is_optional {
+            quote! {#field_name.maybe()}
+        } else {
+            quote! {&#field_name}
+        }
+    });
+    let clones = fields.iter().map(|field| {
+        let field_name = field.ident;
+        if field.is_optional {
+            field.optional_component_into_field(quote! {#field_name.cloned()})
+        } else {
+            field.component_into_field(quote! {Clone::clone(#field_name)})
+        }
+    });
+    let (marker, marker_init) = if has_skipped {
+        let marker = marker_ident(fields);
+        (
+            quote! { #marker: std::marker::PhantomData<fn() -> #ident #ty_generics>, },
+            quote! { #marker: std::marker::PhantomData, },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    let doc = format!("The components of many [`{}`] groups stored as columns. The components \
+        in each row of the columns belong to the entity in the same row of `entities`.", ident);
+    let columns_from_world_doc = format!("Extracts every instance of this group in the world into \
+        a `{}`", columns_ident);
+    let new_doc = format!("Creates a `{}` from the entity of each row and the column of each field",
+        columns_ident);
+    let entities = internal_ident("__entities");
+    let entity = internal_ident("__entity");
+    let columns = internal_ident("__columns");
+    let create_from_columns = create_from_columns_method(vis, &columns_ident, &ty_generics, group_attrs, fields);
+    let std_impls = columns_std_impls(&columns_ident, generics, &field_names, &column_tys, &marker_init);
+
+    quote! {
+        #[doc = #doc]
+        #[allow(dead_code)]
+        #vis struct #columns_ident #generics #where_clause {
+            /// The entity of each row
+            #vis entities: Vec<::specs::Entity>,
+            #( #vis #field_names : #column_tys , )*
+            #marker
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #columns_ident #ty_generics #where_clause {
+            #[doc = #new_doc]
+            #vis fn new(entities: Vec<::specs::Entity>, #( #field_names : #column_tys ),*) -> Self {
+                Self {
+                    entities,
+                    #( #field_names , )*
+                    #marker_init
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #[doc = #columns_from_world_doc]
+            #vis fn columns_from_world(world: &#krate::World) -> #columns_ident #ty_generics {
+                use ::specs::{Join, Entities};
+                let ( #entities, #(#field_names),* ) = world.system_data::<( Entities, #(::specs::ReadStorage<#tys>),* )>();
+                let mut #columns = #columns_ident {
+                    entities: Vec::new(),
+                    #( #field_names : Vec::new(), )*
+                    #marker_init
+                };
+                for ( #entity, #(#field_names),* ) in ( &#entities, #(#joinables),* ).join() {
+                    #columns.entities.push(#entity);
+                    #( #columns.#field_names.push(#clones); )*
+                }
+                #columns
+            }
+
+            #create_from_columns
+        }
+
+        #std_impls
+    }
+}
+
+/// Generates `Clone`, `Debug`, and `PartialEq` impls for the columns struct
+///
+/// The columns can only implement these traits if every column does.
+fn columns_std_impls(
+    columns_ident: &Ident,
+    generics: &Generics,
+    field_names: &[&Ident],
+    column_tys: &[TokenStream],
+    marker_init: &TokenStream,
+) -> TokenStream {
+    let with_bound = |bound: TokenStream| {
+        let mut generics = generics.clone();
+        let where_clause = generics.make_where_clause();
+        for column_ty in column_tys {
+            where_clause.predicates.push(crate::deferred_bound(column_ty, bound.clone()));
+        }
+        generics
+    };
+    let name = columns_ident.to_string();
+    let field_name_strs = field_names.iter().map(|field_name| field_name.unraw().to_string());
+
+    let clone_generics = with_bound(quote! {Clone});
+    let (impl_generics, ty_generics, where_clause) = clone_generics.split_for_impl();
+    let clone_impl = quote! {
+        impl #impl_generics Clone for #columns_ident #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                Self {
+                    entities: Clone::clone(&self.entities),
+                    #( #field_names : Clone::clone(&self.#field_names), )*
+                    #marker_init
+                }
+            }
+        }
+    };
+
+    let debug_generics = with_bound(quote! {std::fmt::Debug});
+    let (impl_generics, ty_generics, where_clause) = debug_generics.split_for_impl();
+    let debug_impl = quote! {
+        impl #impl_generics std::fmt::Debug for #columns_ident #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.debug_struct(#name)
+                    .field("entities", &self.entities)
+                    #( .field(#field_name_strs, &self.#field_names) )*
+                    .finish()
+            }
+        }
+    };
+
+    let eq_generics = with_bound(quote! {PartialEq});
+    let (impl_generics, ty_generics, where_clause) = eq_generics.split_for_impl();
+    let eq_impl = quote! {
+        impl #impl_generics PartialEq for #columns_ident #ty_generics #where_clause {
+            fn eq(&self, other: &Self) -> bool {
+                self.entities == other.entities #( && self.#field_names == other.#field_names )*
+            }
+        }
+    };
+
+    quote! {
+        #clone_impl
+        #debug_impl
+        #eq_impl
+    }
+}
+
+fn create_from_columns_method(
+    vis: &Visibility,
+    columns_ident: &Ident,
+    ty_generics: &TypeGenerics,
+    group_attrs: &GroupAttrs,
+    fields: &[ComponentField],
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
+    let first_field = field_names[0];
+    let tys = fields.iter().map(|f| f.component_ty());
+    let iters: Vec<_> = field_names.iter()
+        .map(|field_name| internal_ident(&format!("__{}_column", field_name.unraw())))
+        .collect();
+    let length_checks = field_names.iter().skip(1).map(|field_name| {
+        let message = format!("expected the `{}` column to have {{}} rows because that is the \
+            number of rows in the `{}` column, but it has {{}} rows", field_name.unraw(), first_field.unraw());
+        quote! {
+            assert!(columns.#field_name.len() == rows, #message, rows, columns.#field_name.len());
+        }
+    });
+    // Every value is validated before anything is created so a panic leaves the world untouched
+    let validations = fields.iter().filter_map(|field| {
+        let ComponentField {ident: field_name, is_optional, ref validate_each, ..} = *field;
+        let validate_each = validate_each.as_ref()?;
+        let message = format!("invalid value for the `{}` field: {{}}", field_name.unraw());
+        let values = if is_optional {
+            quote! { columns.#field_name.iter().flatten() }
+        } else {
+            quote! { &columns.#field_name }
+        };
+        Some(quote! {
+            for value in #values {
+                if let Err(err) = #validate_each(value) {
+                    let err: Box<dyn std::error::Error + Send + Sync> = err.into();
+                    panic!(#message, err);
+                }
+            }
+        })
+    });
+    let tags = &group_attrs.tags;
+    let not_registered_panics = crate::not_registered_panics(fields, &tags.iter().collect::<Vec<_>>(), group_attrs);
+    let after_create = group_attrs.after_create.as_ref().map(|after_create| quote! {
+        for &entity in &entities {
+            #after_create(entity, world);
+        }
+    });
+    let inserts = fields.iter().zip(&iters).map(|(field, iter)| {
+        let ComponentField {ident: field_name, is_optional, ..} = *field;
+        // The length of each column was checked above and the entities were just created
+        let value = quote! {#iter.next().expect("bug: columns should have the same length")};
+        let component = field.field_into_component(quote! {value});
+        let insert = quote! {
+            #field_name.insert(entity, #component).expect("bug: created entity should be alive");
+        };
+        if is_optional {
+            quote! {
+                if let Some(value) = #value {
+                    #insert
+                }
+            }
+        } else {
+            quote! {
+                let value = #value;
+                #insert
+            }
+        }
+    });
+    let doc = format!("Creates a new entity for each row of the given `{}` and adds the components \
+        in that row to it. Returns the created entities in the same order as the rows.\n\n\
+        The `entities` column is ignored. Panics if the columns do not all have the same number \
+        of rows or if any value fails validation.", columns_ident);
+
+    quote! {
+        #[doc = #doc]
+        #vis fn create_from_columns(
+            columns: #columns_ident #ty_generics,
+            world: &mut #krate::World,
+        ) -> Vec<::specs::Entity> {
+            use ::specs::{Builder, WorldExt};
+            #not_registered_panics
+            let rows = columns.#first_field.len();
+            #( #length_checks )*
+            #( #validations )*
+
+            let entities: Vec<_> = (0..rows).map(|_| world.create_entity().build()).collect();
+            {
+                let ( #(mut #field_names),* ) = world.system_data::<( #( ::specs::WriteStorage<#tys> ),* )>();
+                #( let mut #iters = columns.#field_names.into_iter(); )*
+                for &entity in &entities {
+                    #( #inserts )*
+                }
+                #({
+                    let mut tag = world.write_storage::<#tags>();
+                    for &entity in &entities {
+                        tag.insert(entity, Default::default()).expect("bug: created entity should be alive");
+                    }
+                })*
+            }
+            #after_create
+            entities
+        }
+    }
+}
+
+/// Generates an enum with a variant holding the value of each field, as well as an inherent
+/// `apply_fields` method on the group that updates the fields named in a map of those values
+///
+/// If `has_skipped` is true, the group may have type parameters that are only used by its skipped
+/// fields, so a hidden variant is added that uses every type parameter of the group.
+pub fn field_value_enum(
+    ident: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    group_attrs: &GroupAttrs,
+    fields: &[ComponentField],
+    has_skipped: bool,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let value_ident = group_attrs.companion_ident(ident, "FieldValue");
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
+    let names: Vec<_> = field_names.iter().map(|field_name| field_name.unraw().to_string()).collect();
+    let variants: Vec<_> = field_names.iter().map(|field_name| variant_ident(field_name)).collect();
+    // Different field names can convert to the same variant name (e.g. `foo_1` and `foo1`)
+    for (i, variant) in variants.iter().enumerate() {
+        if let Some(j) = variants[..i].iter().position(|other| other == variant) {
+            let message = format!("the `{}` and `{}` fields would both be represented by the `{}` \
+                variant of `{}`, so one of them needs to be renamed", field_names[j].unraw(),
+                field_names[i].unraw(), variant, value_ident);
+            return syn::Error::new(field_names[i].span(), message).to_compile_error();
+        }
+    }
+    let field_tys = fields.iter().map(|&ComponentField {ty, is_optional, ..}| {
+        if is_optional {
+            quote! {Option<#ty>}
+        } else {
+            quote! {#ty}
+        }
+    });
+    let (marker, marker_name) = if has_skipped {
+        (
+            quote! {
+                #[doc(hidden)]
+                __Group(std::marker::PhantomData<fn() -> #ident #ty_generics>),
+            },
+            quote! { #value_ident::__Group(_) => unreachable!(), },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    // The fields are applied without a group to pass to their skip_if functions, so those are
+    // ignored here
+    let unskipped: Vec<_> = fields.iter().cloned()
+        .map(|field| ComponentField {skip_if: None, ..field})
+        .collect();
+    let values = internal_ident("__values");
+    let value = internal_ident("__value");
+    let validations = unskipped.iter().zip(&variants).zip(&names).map(|((field, variant), name)| {
+        let validate = crate::validate_fields(std::slice::from_ref(field), &|_| quote! {#value}, false);
+        quote! {
+            if let Some(#value_ident::#variant(#value)) = #values.get(#name) {
+                #validate
+            }
+        }
+    });
+    let writes = unskipped.iter().zip(&variants).zip(&names).map(|((field, variant), name)| {
+        let write = crate::write_field(field, group_attrs, &|_| quote! {#value}, false, None);
+        quote! {
+            if let Some(#value_ident::#variant(#value)) = #values.get(#name) {
+                let #value = Clone::clone(#value);
+                #write
+            }
+        }
+    });
+    let write_storages = crate::write_storages(fields, group_attrs, false);
+    let bump_version = crate::bump_version(group_attrs, false);
+
+    let body = group_attrs.map_update_error(quote! {()}, quote! {
+        for (name, value) in #values {
+            if name == value.field_name() {
+                continue;
+            }
+            let message = match name.as_str() {
+                #( #names )|* => format!("expected a value for the `{}` field, but got a value \
+                    for the `{}` field", name, value.field_name()),
+                _ if ignore_unknown => continue,
+                _ => format!("`{}` is not a field of this group", name),
+            };
+            return Err(::specs::error::Error::Custom(::specs::error::BoxedErr(message.into())));
+        }
+        #( #validations )*
+
+        #write_storages
+        #( #writes )*
+        #bump_version
+
+        Ok(())
+    }, false);
+
+    let doc = format!("The value of one of the fields of [`{}`], used to update fields by name \
+        with `apply_fields`", ident);
+
+    quote! {
+        #[doc = #doc]
+        #[allow(dead_code)]
+        #vis enum #value_ident #generics #where_clause {
+            #( #variants(#field_tys), )*
+            #marker
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #value_ident #ty_generics #where_clause {
+            /// Returns the name of the field that this value is for
+            #vis fn field_name(&self) -> &'static str {
+                match self {
+                    #( #value_ident::#variants(_) => #names, )*
+                    #marker_name
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Updates the fields of the given entity that are named in the given map the same way
+            /// as `ComponentGroup::update`. Fields that are not in the map are left unchanged.
+            ///
+            /// Returns an error without writing anything if a value is for a different field than
+            /// its name or fails validation. Names that are not fields of the group are ignored if
+            /// `ignore_unknown` is true and return an error otherwise.
+            #vis fn apply_fields(
+                world: &mut #krate::World,
+                entity: ::specs::Entity,
+                #values: &std::collections::HashMap<String, #value_ident #ty_generics>,
+                ignore_unknown: bool,
+            ) -> Result<(), <Self as #krate::ComponentGroup>::UpdateError> {
+                #body
+            }
+        }
+    }
+}
+
+/// Converts the name of a field to the name of an enum variant (e.g. `max_speed` to `MaxSpeed`)
+fn variant_ident(field_name: &Ident) -> Ident {
+    let name: String = field_name.unraw().to_string().split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            // Parts are never empty, so there is always a first character
+            let first = chars.next().unwrap();
+            first.to_uppercase().chain(chars).collect::<String>()
+        })
+        .collect();
+    Ident::new(&name, field_name.span())
+}
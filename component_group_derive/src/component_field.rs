@@ -1,3 +1,5 @@
+use std::convert::TryFrom;
+
 use syn::{
     Ident,
     Type,
@@ -8,29 +10,55 @@ use syn::{
     AngleBracketedGenericArguments,
     GenericArgument,
     Field,
+    Meta,
+    MetaNameValue,
+    spanned::Spanned,
+    ext::IdentExt,
 };
 
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::group_attrs::{component_group_metas, lit_str, lit_usize};
+
 /// Returns the inner type of the Option if the given path represents the Option type
+///
+/// The path must either be `Option<T>` or `std::option::Option<T>` / `core::option::Option<T>`
+/// (optionally with a leading `::`). Types named `Option` from any other module are not matched.
 fn inner_option_type(path: &Path) -> Option<&Type> {
-    match path {
-        // This is a naive test
-        Path {leading_colon: None, segments} if segments.len() == 1 => {
-            // Safe unwrap because we already checked the length
-            let last = segments.last().unwrap();
-            match last {
-                PathSegment {
-                    ident: type_name,
-                    arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments {
-                        colon2_token: None,
-                        args,
-                        ..
-                    }),
-                } if type_name == "Option" && args.len() == 1 => {
-                    match args.last().unwrap() {
-                        GenericArgument::Type(ty) => Some(ty),
-                        _ => None,
-                    }
-                },
+    let Path {leading_colon, segments} = path;
+    let is_option_path = match segments.len() {
+        1 => leading_colon.is_none(),
+        3 => {
+            let root = &segments[0];
+            let module = &segments[1];
+            (root.ident == "std" || root.ident == "core") && root.arguments.is_empty()
+                && module.ident == "option" && module.arguments.is_empty()
+        },
+        _ => false,
+    };
+    if is_option_path {
+        inner_option_segment_type(path)
+    } else {
+        None
+    }
+}
+
+/// Returns the inner type of the Option if the last segment of the given path is `Option<T>`
+///
+/// Unlike `inner_option_type`, this also accepts qualified paths like `std::option::Option<T>`
+fn inner_option_segment_type(path: &Path) -> Option<&Type> {
+    match path.segments.last()? {
+        PathSegment {
+            ident: type_name,
+            arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments {
+                colon2_token: None,
+                args,
+                ..
+            }),
+        } if type_name == "Option" && args.len() == 1 => {
+            match args.last().unwrap() {
+                GenericArgument::Type(ty) => Some(ty),
                 _ => None,
             }
         },
@@ -38,37 +66,196 @@ fn inner_option_type(path: &Path) -> Option<&Type> {
     }
 }
 
+/// Returns true if the field is marked with `#[component_group(skip)]`, meaning that it is not a
+/// component and should be ignored by every generated method
+pub fn is_skipped_field(field: &Field) -> syn::Result<bool> {
+    let metas = component_group_metas(&field.attrs)?;
+    let skip = metas.iter().find(|meta| match meta {
+        Meta::Path(path) => path.is_ident("skip"),
+        _ => false,
+    });
+    match skip {
+        Some(skip) if metas.len() > 1 => Err(syn::Error::new(skip.span(),
+            "#[component_group(skip)] cannot be combined with other component_group field attributes")),
+        Some(_) => Ok(true),
+        None => Ok(false),
+    }
+}
+
 /// One of the Components in a group, potentially optional
 ///
-/// The ty field of this struct is assumed to implement Component
+/// The ty field of this struct is the type of the field (without the Option). It is assumed to
+/// implement Component unless a different component type is given with the component attribute.
 /// is_optional represents that this type may not be present in the World and that we should
 /// store None if that is the case
+#[derive(Clone)]
 pub struct ComponentField<'a> {
     pub ident: &'a Ident,
     pub ty: &'a Type,
+    /// The type of the component stored in the world if it is different from the field type. The
+    /// two types are converted between with `Into`.
+    pub component: Option<Type>,
     pub is_optional: bool,
+    /// If true, equal values of this component are replaced with clones of a single value when
+    /// creating many groups at once
+    pub intern: bool,
+    /// A function `fn(&T) -> Result<(), E>` that must succeed before this component is written
+    pub validate_each: Option<Path>,
+    /// A function `fn(&Self) -> bool` that skips writing this component when it returns true
+    pub skip_if: Option<Path>,
+    /// If true, the component is intended to be a marker with no data (e.g. using `NullStorage`)
+    pub tag: bool,
+    /// A function `fn(C) -> T` that converts the component read from the world into the value of
+    /// the field, used instead of `Into`
+    pub read_with: Option<Path>,
+    /// The position of this field when the components are inserted by `create` and `update`, if
+    /// it is different from the position of the field in the struct
+    pub order: Option<usize>,
+    /// If true, the field has an `Option` type, but its component is required. The field must be
+    /// `Some` when it is written and it is always `Some` when it is read.
+    pub required: bool,
 }
 
-impl<'a> From<&'a Field> for ComponentField<'a> {
-    fn from(Field {ident, ty, ..}: &'a Field) -> Self {
-        let (ty, is_optional) = match ty {
-            // Matching Option is not very sophisticated here. We just look for a type == "Option"
-            // That means that using the fully-qualified name would fail.
-            Type::Path(TypePath {
-                qself: None,
-                path,
-            }) => match inner_option_type(path) {
-                Some(ty) => (ty, true),
+impl<'a> TryFrom<&'a Field> for ComponentField<'a> {
+    type Error = syn::Error;
+
+    fn try_from(Field {ident, ty, attrs, ..}: &'a Field) -> syn::Result<Self> {
+        let mut intern = false;
+        let mut optional = false;
+        let mut validate_each = None;
+        let mut skip_if = None;
+        let mut component = None;
+        let mut tag = false;
+        let mut read_with = None;
+        let mut order = None;
+        let mut required = None;
+        for meta in component_group_metas(attrs)? {
+            match meta {
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("validate_each") => {
+                    validate_each = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("component") => {
+                    component = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("read_with") => {
+                    read_with = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("skip_if") => {
+                    skip_if = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("order") => {
+                    order = Some(lit_usize(lit)?);
+                },
+                Meta::Path(ref path) if path.is_ident("intern") => intern = true,
+                Meta::Path(ref path) if path.is_ident("optional") => optional = true,
+                Meta::Path(ref path) if path.is_ident("tag") => tag = true,
+                Meta::Path(ref path) if path.is_ident("required") => required = Some(path.span()),
+                _ => return Err(syn::Error::new(meta.span(), "unknown component_group field attribute")),
+            }
+        }
+
+        if let Some(span) = required {
+            if optional || component.is_some() || validate_each.is_some() {
+                return Err(syn::Error::new(span, "#[component_group(required)] cannot be combined \
+                    with the optional, component, or validate_each attributes"));
+            }
+            // The component is the type inside of the Option and the field is converted to and
+            // from it by unwrapping it or wrapping it in Some
+            let inner_ty = match ty {
+                Type::Path(TypePath {qself: None, path}) => inner_option_segment_type(path),
+                _ => None,
+            };
+            match inner_ty {
+                Some(inner_ty) => component = Some(inner_ty.clone()),
+                None => return Err(syn::Error::new(ty.span(),
+                    "fields marked with #[component_group(required)] must have an Option type")),
+            }
+        }
+
+        let (ty, is_optional) = if required.is_some() {
+            (ty, false)
+        } else if optional {
+            // The field is explicitly marked as optional, so any path that ends with Option<T> is
+            // accepted and anything else is an error
+            let inner_ty = match ty {
+                Type::Path(TypePath {qself: None, path}) => inner_option_segment_type(path),
+                _ => None,
+            };
+            match inner_ty {
+                Some(inner_ty) => (inner_ty, true),
+                None => return Err(syn::Error::new(ty.span(),
+                    "fields marked with #[component_group(optional)] must have an Option type")),
+            }
+        } else {
+            match ty {
+                // Only the paths that refer to the Option type from std or core are matched.
+                // Any other path (e.g. a re-export or type alias) requires the field to be marked
+                // with #[component_group(optional)].
+                Type::Path(TypePath {
+                    qself: None,
+                    path,
+                }) => match inner_option_type(path) {
+                    Some(ty) => (ty, true),
+                    _ => (ty, false),
+                },
                 _ => (ty, false),
-            },
-            _ => (ty, false),
+            }
         };
 
-        Self {
+        Ok(Self {
             // Fields from NamedFields always have field names
             ident: ident.as_ref().unwrap(),
             ty,
+            component,
             is_optional,
+            intern,
+            validate_each,
+            skip_if,
+            tag,
+            read_with,
+            order,
+            required: required.is_some(),
+        })
+    }
+}
+
+impl<'a> ComponentField<'a> {
+    /// The type of the component stored in the world for this field
+    pub fn component_ty(&self) -> &Type {
+        self.component.as_ref().unwrap_or(self.ty)
+    }
+
+    /// Converts an expression with the type of this field into the component type
+    pub fn field_into_component(&self, value: TokenStream) -> TokenStream {
+        let ty = self.ty;
+        match self.component {
+            Some(_) if self.required => {
+                let message = format!("the `{}` field is required, but it was None", self.ident.unraw());
+                quote! { #value.expect(#message) }
+            },
+            Some(ref component) => quote! { <#ty as Into<#component>>::into(#value) },
+            None => value,
+        }
+    }
+
+    /// Converts an expression with the component type into the type of this field
+    pub fn component_into_field(&self, value: TokenStream) -> TokenStream {
+        let ty = self.ty;
+        match (&self.read_with, &self.component) {
+            (Some(read_with), _) => quote! { #read_with(#value) },
+            (None, Some(_)) if self.required => quote! { Some(#value) },
+            (None, Some(component)) => quote! { <#component as Into<#ty>>::into(#value) },
+            (None, None) => value,
+        }
+    }
+
+    /// Converts an expression with the type `Option<C>` where `C` is the component type into
+    /// `Option<T>` where `T` is the type of this field
+    pub fn optional_component_into_field(&self, value: TokenStream) -> TokenStream {
+        if self.component.is_none() && self.read_with.is_none() {
+            return value;
         }
+        let converted = self.component_into_field(quote! {value});
+        quote! { #value.map(|value| #converted) }
     }
 }
@@ -0,0 +1,213 @@
+use syn::{
+    Attribute,
+    Meta,
+    MetaList,
+    MetaNameValue,
+    NestedMeta,
+    Lit,
+    LitStr,
+    Path,
+    Type,
+    Ident,
+    spanned::Spanned,
+};
+use proc_macro2::TokenStream;
+use quote::{quote, format_ident};
+
+/// Options for the entire group, specified with `#[component_group(...)]` on the struct
+#[derive(Default)]
+pub struct GroupAttrs {
+    /// If true, `remove` uses `Default::default()` for required components that are missing
+    /// instead of panicking
+    pub ignore_missing_on_remove: bool,
+    /// If true, updates log a warning whenever a component is removed because its optional field
+    /// is None
+    pub warn_on_remove: bool,
+    /// If true, `create` panics instead of creating an entity without any components
+    pub deny_empty: bool,
+    /// If true, the struct must have exactly one field and derefs to that field
+    pub transparent: bool,
+    /// A function `fn(Entity, &mut World)` to call at the end of `create`
+    pub after_create: Option<Path>,
+    /// A function `fn(&mut Self, &HashMap<Entity, Entity>)` that implements `remap_entities`
+    pub remap_entities: Option<Path>,
+    /// The prefix used to name generated companion types instead of the struct name
+    pub alias: Option<Ident>,
+    /// If true, nothing that requires the components to implement `Clone` is generated
+    /// (`clone_bound = false`)
+    pub without_clone: bool,
+    /// Marker components that are added with their default value in `create` and stripped in
+    /// `remove`
+    pub tags: Vec<Type>,
+    /// Components that are not in the group but are taken out of the entity by
+    /// `remove_with_extras`
+    pub extras: Vec<Type>,
+    /// A component that is incremented with the `Increment` trait every time the group is updated
+    pub version: Option<Type>,
+    /// The error type returned by the update methods instead of `specs::error::Error`
+    pub update_error: Option<Type>,
+    /// A function `fn(specs::error::Error) -> E` that converts errors into the `update_error`
+    /// type, used instead of `From`
+    pub map_err: Option<Path>,
+    /// The path to the component_group crate, for when it is only available through a re-export
+    pub crate_path: Option<Path>,
+}
+
+impl GroupAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut group_attrs = Self::default();
+
+        for meta in component_group_metas(attrs)? {
+            match meta {
+                Meta::Path(ref path) if path.is_ident("ignore_missing_on_remove") => {
+                    group_attrs.ignore_missing_on_remove = true;
+                },
+                Meta::Path(ref path) if path.is_ident("warn_on_remove") => {
+                    group_attrs.warn_on_remove = true;
+                },
+                Meta::Path(ref path) if path.is_ident("deny_empty") => {
+                    group_attrs.deny_empty = true;
+                },
+                Meta::Path(ref path) if path.is_ident("transparent") => {
+                    group_attrs.transparent = true;
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("after_create") => {
+                    group_attrs.after_create = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("remap_entities") => {
+                    group_attrs.remap_entities = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("alias") => {
+                    group_attrs.alias = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("with") => {
+                    group_attrs.tags.push(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("capture_extras") => {
+                    group_attrs.extras.push(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("version") => {
+                    group_attrs.version = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("update_error") => {
+                    group_attrs.update_error = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("map_err") => {
+                    group_attrs.map_err = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("crate") => {
+                    group_attrs.crate_path = Some(lit_str(lit)?.parse()?);
+                },
+                Meta::NameValue(MetaNameValue {ref path, ref lit, ..}) if path.is_ident("clone_bound") => {
+                    group_attrs.without_clone = !lit_bool(lit)?;
+                },
+                _ => return Err(syn::Error::new(meta.span(), "unknown component_group attribute")),
+            }
+        }
+
+        if let (Some(map_err), None) = (&group_attrs.map_err, &group_attrs.update_error) {
+            return Err(syn::Error::new(map_err.span(),
+                "map_err requires the error type to be specified with update_error"));
+        }
+
+        Ok(group_attrs)
+    }
+
+    /// Returns the error type of the generated update methods
+    pub fn update_error(&self) -> TokenStream {
+        match self.update_error {
+            Some(ref ty) => quote!(#ty),
+            None => quote!(::specs::error::Error),
+        }
+    }
+
+    /// Wraps `body`, which evaluates to `Result<ok, specs::error::Error>` and may return early
+    /// with such a result, so that it evaluates to a result with the `update_error` type instead
+    ///
+    /// If `tagged` is true, the error in the result is a tuple of a field name and the error.
+    pub fn map_update_error(&self, ok: TokenStream, body: TokenStream, tagged: bool) -> TokenStream {
+        let ty = match self.update_error {
+            Some(ref ty) => ty,
+            None => return body,
+        };
+        let map_err = match self.map_err {
+            Some(ref map_err) => quote!(#map_err),
+            None => quote!(<#ty as From<::specs::error::Error>>::from),
+        };
+        let (err, map_err) = if tagged {
+            (quote! {(&'static str, ::specs::error::Error)}, quote! {|(name, err)| (name, #map_err(err))})
+        } else {
+            (quote! {::specs::error::Error}, map_err)
+        };
+        quote! {
+            // The closure gives the early returns in the body a place to return to so that every
+            // error can be converted
+            #[allow(clippy::redundant_closure_call)]
+            let result = (move || -> Result<#ok, #err> { #body })();
+            result.map_err(#map_err)
+        }
+    }
+
+    /// Returns the path that the generated code should use to refer to the component_group crate
+    pub fn crate_path(&self) -> TokenStream {
+        match self.crate_path {
+            Some(ref path) => quote!(#path),
+            None => quote!(::component_group),
+        }
+    }
+
+    /// Returns the name of the companion type with the given suffix for the given struct
+    pub fn companion_ident(&self, ident: &Ident, suffix: &str) -> Ident {
+        let prefix = self.alias.as_ref().unwrap_or(ident);
+        format_ident!("{}{}", prefix, suffix)
+    }
+}
+
+/// Returns the items listed within every `#[component_group(...)]` attribute
+pub fn component_group_metas(attrs: &[Attribute]) -> syn::Result<Vec<Meta>> {
+    let mut metas = Vec::new();
+    for attr in attrs {
+        if !attr.path.is_ident("component_group") {
+            continue;
+        }
+
+        match attr.parse_meta()? {
+            Meta::List(MetaList {nested, ..}) => for item in nested {
+                match item {
+                    NestedMeta::Meta(meta) => metas.push(meta),
+                    NestedMeta::Lit(lit) => {
+                        return Err(syn::Error::new(lit.span(), "expected a component_group attribute"));
+                    },
+                }
+            },
+            meta => {
+                return Err(syn::Error::new(meta.span(), "expected #[component_group(...)]"));
+            },
+        }
+    }
+    Ok(metas)
+}
+
+/// Returns the value of the boolean literal or an error if the literal is not a boolean
+pub fn lit_bool(lit: &Lit) -> syn::Result<bool> {
+    match lit {
+        Lit::Bool(lit) => Ok(lit.value),
+        _ => Err(syn::Error::new(lit.span(), "expected true or false")),
+    }
+}
+
+/// Returns the value of the integer literal or an error if the literal is not an integer
+pub fn lit_usize(lit: &Lit) -> syn::Result<usize> {
+    match lit {
+        Lit::Int(lit) => lit.base10_parse(),
+        _ => Err(syn::Error::new(lit.span(), "expected an integer")),
+    }
+}
+
+/// Returns the string literal or an error if the literal is not a string
+pub fn lit_str(lit: &Lit) -> syn::Result<&LitStr> {
+    match lit {
+        Lit::Str(lit) => Ok(lit),
+        _ => Err(syn::Error::new(lit.span(), "expected a string literal")),
+    }
+}
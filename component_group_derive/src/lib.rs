@@ -4,7 +4,11 @@
 
 extern crate proc_macro;
 
+use std::convert::TryFrom;
+
 mod component_field;
+mod companions;
+mod group_attrs;
 
 use syn::{
     DeriveInput,
@@ -15,20 +19,32 @@ use syn::{
     Fields,
     Ident,
     Generics,
+    Visibility,
+    Type,
+    TypePath,
     FieldsNamed,
     Field,
     parse_macro_input,
+    WherePredicate,
+    parse_quote,
+    spanned::Spanned,
+    ext::IdentExt,
     token::{Struct, Enum, Union},
 };
 use proc_macro2::{TokenStream, Span};
-use quote::quote;
+use quote::{quote, quote_spanned, format_ident, ToTokens};
 
-use crate::component_field::ComponentField;
+use crate::component_field::{ComponentField, is_skipped_field};
+use crate::group_attrs::GroupAttrs;
 
-#[proc_macro_derive(ComponentGroup)]
+#[proc_macro_derive(ComponentGroup, attributes(component_group))]
 pub fn derive_component_group(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // Parse the input tokens into a syntax tree
-    let DeriveInput {ident, generics, data, ..} = parse_macro_input!(input as DeriveInput);
+    let DeriveInput {ident, vis, generics, data, attrs} = parse_macro_input!(input as DeriveInput);
+    let group_attrs = match GroupAttrs::parse(&attrs) {
+        Ok(group_attrs) => group_attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
     match data {
         Data::Struct(DataStruct {
@@ -39,7 +55,7 @@ pub fn derive_component_group(input: proc_macro::TokenStream) -> proc_macro::Tok
             if fields.is_empty() {
                 error(span, "struct must have at least one field to derive ComponentGroup")
             } else {
-                impl_component_group(ident, &generics, fields.iter())
+                impl_component_group(ident, &vis, &generics, &group_attrs, fields.iter())
             }.into()
         },
         Data::Struct(DataStruct {struct_token: Struct {span}, ..}) |
@@ -57,141 +73,2227 @@ fn error(span: Span, message: &str) -> TokenStream {
 /// Generates an impl of the ComponentGroup trait for the given struct
 fn impl_component_group<'a>(
     ident: Ident,
+    vis: &Visibility,
     generics: &'a Generics,
+    group_attrs: &GroupAttrs,
     fields: impl Iterator<Item=&'a Field>,
 ) -> TokenStream {
+    let krate = group_attrs.crate_path();
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-    let fields: Vec<_> = fields.map(ComponentField::from).collect();
+    // The order of the fields is significant. Every generated method reads, inserts, and removes
+    // components in the order that the fields are declared in the struct. This keeps the order of
+    // the events emitted by flagged storages consistent between runs. The only exception is that
+    // `create` and `update` insert the components in the order given by the `order` attribute.
+    //
+    // PhantomData fields and fields marked with #[component_group(skip)] are not components, so
+    // they are skipped and filled in with their default value whenever the struct is constructed.
+    let mut skipped = Vec::new();
+    let mut component_fields = Vec::new();
+    for field in fields {
+        match is_skipped_field(field) {
+            Ok(true) => skipped.push(field),
+            Ok(false) if is_phantom_data(&field.ty) => skipped.push(field),
+            Ok(false) => component_fields.push(field),
+            Err(err) => return err.to_compile_error(),
+        }
+    }
+    let fields = component_fields;
+    if fields.is_empty() {
+        return error(ident.span(), "struct must have at least one component field to derive ComponentGroup");
+    }
+    let fields = match fields.into_iter().map(ComponentField::try_from).collect::<syn::Result<Vec<_>>>() {
+        Ok(fields) => fields,
+        Err(err) => return err.to_compile_error(),
+    };
+    if group_attrs.transparent && (fields.len() != 1 || !skipped.is_empty()) {
+        return error(ident.span(), "struct must have exactly one field to use #[component_group(transparent)]");
+    }
+    let has_skipped = !skipped.is_empty();
+    // Fields without an `order` attribute are ordered by their position in the struct. The sort is
+    // stable, so fields with the same order keep the order that they are declared in.
+    let mut ordered_fields: Vec<_> = fields.iter().cloned().enumerate().collect();
+    ordered_fields.sort_by_key(|(index, field)| field.order.unwrap_or(*index));
+    let ordered_fields: Vec<_> = ordered_fields.into_iter().map(|(_, field)| field).collect();
+    let ordered_field_names: Vec<_> = ordered_fields.iter().map(|f| f.ident).collect();
+    let skipped = skipped_fields(&skipped);
     let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
-    let first_from_world = first_from_world_method(&field_names, &fields);
-    let from_world = from_world_method(&field_names, &fields);
-    let create = create_method(&fields);
-    let update = update_method(&field_names, &fields);
-    let remove = remove_method(&field_names, &fields);
-    quote! {
-        impl #impl_generics component_group::ComponentGroup for #ident #ty_generics #where_clause {
-            #first_from_world
-            #from_world
+    let groups = internal_ident("__groups");
+    let join_groups = join_groups(&groups, &field_names, &fields, &skipped, None, None);
+    // Reading a group requires cloning its components, so groups without the Clone bound cannot
+    // implement ReadableGroup. This makes every method that reads them fail to compile.
+    let readable = if group_attrs.without_clone {
+        quote! {}
+    } else {
+        let first_from_world = first_from_world_method(&groups, &join_groups, group_attrs);
+        let from_world = from_world_method(&field_names, &fields, &skipped, group_attrs);
+        quote! {
+            impl #impl_generics #krate::ReadableGroup for #ident #ty_generics #where_clause {
+                #first_from_world
+                #from_world
+            }
+        }
+    };
+    let create = create_method(&ordered_fields, group_attrs);
+    let create_on = create_on_method(&ordered_fields, group_attrs);
+    let create_many = create_many_method(&ordered_fields, group_attrs);
+    let update = update_method(&ordered_field_names, &ordered_fields, group_attrs, &skipped);
+    let remove = remove_method(&fields, group_attrs, &skipped);
+    let remap_entities = remap_entities_method(group_attrs);
+    let try_update = try_update_method(vis, &fields, group_attrs);
+    let component_names_present = component_names_present_method(&fields, group_attrs);
+    let fields_ident = group_attrs.companion_ident(&ident, "Fields");
+    let update_returning_removed = update_returning_removed_method(vis, &fields, group_attrs);
+    let update_with_events = update_with_events_method(vis, &fields, group_attrs);
+    let update_selected = update_selected_method(vis, &fields, group_attrs, &fields_ident);
+    let matches = matches_method(vis, &fields, group_attrs);
+    let (find_from_world, from_world_filtered, join_with, from_world_first_missing) = if group_attrs.without_clone {
+        (quote! {}, quote! {}, quote! {}, quote! {})
+    } else {
+        (
+            find_from_world_method(vis, &groups, &join_groups, group_attrs),
+            from_world_filtered_method(vis, &groups, &field_names, &fields, &skipped, group_attrs),
+            join_with_method(vis, &groups, &field_names, &fields, &skipped, group_attrs),
+            from_world_first_missing_method(vis, &field_names, &fields, &skipped, group_attrs),
+        )
+    };
+    let field_names_methods = field_names_methods(vis, &fields);
+    let lazy_build = lazy_build_method(vis, &fields, group_attrs);
+    let edit_first = edit_first_method(vis, &field_names, &fields, group_attrs);
+    let extend_builder = extend_builder_method(vis, &fields, group_attrs);
+    let attach_lazy = attach_lazy_method(vis, &fields, group_attrs);
+    let assert_matches_world = assert_matches_world_method(vis, &fields, group_attrs);
+    let json_methods = json_methods(vis, &fields, group_attrs, &skipped);
+    let remove_with_extras = remove_with_extras_method(vis, group_attrs);
+    let remove_audited = remove_audited_method(vis, &fields, group_attrs);
+    let clear_optionals = clear_optionals_method(vis, &fields, group_attrs);
+    let take_all_from_world = take_all_from_world_method(vis, &fields, group_attrs);
+    let setup = setup_method(vis, &fields, group_attrs);
+    let read_struct = companions::read_struct(&ident, vis, generics, group_attrs, &fields);
+    let fields_struct = companions::fields_struct(&ident, vis, group_attrs, &fields);
+    let readers_struct = companions::readers_struct(&ident, vis, generics, group_attrs, &fields);
+    let (par_set_all, copy_methods, update_stats, columns_struct, field_value_enum) = if group_attrs.without_clone {
+        (quote! {}, quote! {}, quote! {}, quote! {}, quote! {})
+    } else {
+        (
+            par_set_all_method(vis, &fields, group_attrs),
+            copy_methods(vis, &fields, group_attrs),
+            update_stats_method(vis, &fields, group_attrs),
+            companions::columns_struct(&ident, vis, generics, group_attrs, &fields, has_skipped),
+            companions::field_value_enum(&ident, vis, generics, group_attrs, &fields, has_skipped),
+        )
+    };
+    let transparent = transparent_impls(&ident, generics, group_attrs, &fields);
+    let serde = serde_impls(&ident, generics, group_attrs, &fields, &skipped);
+    quote! {
+        impl #impl_generics #krate::ComponentGroup for #ident #ty_generics #where_clause {
             #create
+            #create_on
+            #create_many
             #update
             #remove
+            #component_names_present
+            #remap_entities
+        }
+
+        #readable
+
+        #[allow(dead_code)]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #try_update
+            #update_selected
+            #update_returning_removed
+            #update_with_events
+            #update_stats
+            #matches
+            #from_world_first_missing
+            #find_from_world
+            #from_world_filtered
+            #join_with
+            #edit_first
+            #field_names_methods
+            #lazy_build
+            #extend_builder
+            #attach_lazy
+            #assert_matches_world
+            #par_set_all
+            #json_methods
+            #remove_with_extras
+            #remove_audited
+            #clear_optionals
+            #take_all_from_world
+            #copy_methods
+            #setup
+        }
+
+        #transparent
+        #serde
+        #read_struct
+        #fields_struct
+        #readers_struct
+        #columns_struct
+        #field_value_enum
+    }
+}
+
+/// Generates `Deref` and `DerefMut` impls that give access to the only field of a group with the
+/// `transparent` attribute
+fn transparent_impls(
+    ident: &Ident,
+    generics: &Generics,
+    group_attrs: &GroupAttrs,
+    fields: &[ComponentField],
+) -> TokenStream {
+    if !group_attrs.transparent {
+        return quote! {};
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let ComponentField {ident: field_name, ty, is_optional, ..} = fields[0];
+    let target = if is_optional {
+        quote! {Option<#ty>}
+    } else {
+        quote! {#ty}
+    };
+    quote! {
+        impl #impl_generics ::std::ops::Deref for #ident #ty_generics #where_clause {
+            type Target = #target;
+
+            fn deref(&self) -> &Self::Target {
+                &self.#field_name
+            }
+        }
+
+        impl #impl_generics ::std::ops::DerefMut for #ident #ty_generics #where_clause {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.#field_name
+            }
         }
     }
 }
 
-fn first_from_world_method(field_names: &[&Ident], fields: &[ComponentField]) -> TokenStream {
-    let joinables = fields.into_iter().map(|&ComponentField {ident: field_name, is_optional, ..}| {
+/// Generates `Serialize` and `Deserialize` impls that represent the group as a struct with a key
+/// for the value of each field. Optional fields that are missing are deserialized as `None`. The
+/// impls are only generated if the `serde` feature of the crate is enabled.
+fn serde_impls(
+    ident: &Ident,
+    generics: &Generics,
+    group_attrs: &GroupAttrs,
+    fields: &[ComponentField],
+    skipped: &TokenStream,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
+    let names: Vec<_> = field_names.iter().map(|field_name| field_name.unraw().to_string()).collect();
+    let tys: Vec<_> = fields.iter().map(|f| f.ty).collect();
+    let name = ident.to_string();
+    let field_count = fields.len();
+
+    let mut ser_generics = generics.clone();
+    let where_clause = ser_generics.make_where_clause();
+    for ty in &tys {
+        where_clause.predicates.push(deferred_bound(ty, quote! {#krate::serde::Serialize}));
+    }
+    let (impl_generics, ty_generics, where_clause) = ser_generics.split_for_impl();
+    let serialize_impl = quote! {
+        impl #impl_generics #krate::serde::Serialize for #ident #ty_generics #where_clause {
+            fn serialize<__S>(&self, serializer: __S) -> Result<__S::Ok, __S::Error>
+                where __S: #krate::serde::Serializer,
+            {
+                use #krate::serde::ser::SerializeStruct;
+                let mut state = serializer.serialize_struct(#name, #field_count)?;
+                #( state.serialize_field(#names, &self.#field_names)?; )*
+                state.end()
+            }
+        }
+    };
+
+    let mut de_generics = generics.clone();
+    de_generics.params.insert(0, parse_quote!('__de));
+    let where_clause = de_generics.make_where_clause();
+    for ty in &tys {
+        where_clause.predicates.push(deferred_bound(ty, quote! {#krate::serde::de::DeserializeOwned}));
+    }
+    let (de_impl_generics, _, de_where_clause) = de_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let values: Vec<_> = field_names.iter()
+        .map(|field_name| internal_ident(&format!("__{}_value", field_name.unraw())))
+        .collect();
+    let value_tys = fields.iter().map(|field| {
+        let ty = field.ty;
+        if field.is_optional {
+            quote! {::std::option::Option<#ty>}
+        } else {
+            quote! {#ty}
+        }
+    });
+    let results = fields.iter().zip(&values).zip(&names).map(|((field, value), name)| {
+        if field.is_optional {
+            quote! { #value.unwrap_or(None) }
+        } else {
+            quote! {
+                match #value {
+                    Some(value) => value,
+                    None => return Err(<__M::Error as #krate::serde::de::Error>::missing_field(#name)),
+                }
+            }
+        }
+    });
+    let expecting = format!("struct {}", name);
+    let visitor = internal_ident("__Visitor");
+    let deserialize_impl = quote! {
+        impl #de_impl_generics #krate::serde::Deserialize<'__de> for #ident #ty_generics #de_where_clause {
+            fn deserialize<__D>(deserializer: __D) -> Result<Self, __D::Error>
+                where __D: #krate::serde::Deserializer<'__de>,
+            {
+                struct #visitor<__T>(::std::marker::PhantomData<__T>);
+
+                impl #de_impl_generics #krate::serde::de::Visitor<'__de> for #visitor<#ident #ty_generics> #de_where_clause {
+                    type Value = #ident #ty_generics;
+
+                    fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                        formatter.write_str(#expecting)
+                    }
+
+                    fn visit_map<__M>(self, mut map: __M) -> Result<Self::Value, __M::Error>
+                        where __M: #krate::serde::de::MapAccess<'__de>,
+                    {
+                        #( let mut #values: ::std::option::Option<#value_tys> = None; )*
+                        while let Some(key) = map.next_key::<::std::string::String>()? {
+                            match key.as_str() {
+                                #(
+                                    #names => {
+                                        if #values.is_some() {
+                                            return Err(<__M::Error as #krate::serde::de::Error>::duplicate_field(#names));
+                                        }
+                                        #values = Some(map.next_value()?);
+                                    },
+                                )*
+                                _ => {
+                                    map.next_value::<#krate::serde::de::IgnoredAny>()?;
+                                },
+                            }
+                        }
+                        Ok(#ident {
+                            #( #field_names : #results ),*
+                            #skipped
+                        })
+                    }
+                }
+
+                const FIELDS: &[&str] = &[ #(#names),* ];
+                deserializer.deserialize_struct(#name, FIELDS, #visitor(::std::marker::PhantomData))
+            }
+        }
+    };
+
+    quote! {
+        #krate::__serde! {
+            #serialize_impl
+            #deserialize_impl
+        }
+    }
+}
+
+/// Returns true if the given type is `PhantomData`
+fn is_phantom_data(ty: &Type) -> bool {
+    match ty {
+        Type::Path(TypePath {qself: None, path}) => path.segments.last()
+            .map(|segment| segment.ident == "PhantomData")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Generates the initializers for the skipped fields of the struct. The tokens start with a comma
+/// so they can be placed right after the initializers for the component fields.
+fn skipped_fields(skipped: &[&Field]) -> TokenStream {
+    // Fields from NamedFields always have field names
+    let field_names = skipped.iter().map(|field| field.ident.as_ref().unwrap());
+    quote! { #( , #field_names : Default::default() )* }
+}
+
+/// Returns an identifier for a binding that is only used within the generated code
+///
+/// The identifier has mixed site hygiene, so it can never collide with the bindings created for
+/// each field, even if a field happens to have the same name.
+fn internal_ident(name: &str) -> Ident {
+    Ident::new(name, Span::mixed_site())
+}
+
+/// Returns a where clause predicate that requires `ty` to satisfy `bound`
+///
+/// The predicate is higher-ranked over an unused lifetime, so it is only checked when the item it
+/// is on is used instead of when the group is derived. Otherwise, groups with components that do
+/// not satisfy the bound would not compile, even if they never used that item.
+pub(crate) fn deferred_bound(ty: impl ToTokens, bound: TokenStream) -> WherePredicate {
+    parse_quote!(for<'__a> #ty: #bound)
+}
+
+/// Returns the name of a type as it was written in the struct (e.g. `foo::Bar` or `Vec<u32>`)
+///
+/// The string produced by quote puts spaces between every token (e.g. `foo :: Bar`), so the only
+/// spaces that are kept are the ones that separate two words (e.g. in `dyn Trait`).
+fn type_name(ty: &Type) -> String {
+    let tokens = quote!(#ty).to_string();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let chars: Vec<char> = tokens.chars().collect();
+    let separates_words = |i: usize| {
+        i > 0 && i + 1 < chars.len() && is_word(chars[i - 1]) && is_word(chars[i + 1])
+    };
+    let mut name = String::with_capacity(tokens.len());
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            ' ' if !separates_words(i) => {},
+            ',' => name.push_str(", "),
+            _ => name.push(c),
+        }
+    }
+    name
+}
+
+/// Generates statements that fetch the storages of the group and bind `groups` to an iterator
+/// over every entity that has all of the required components in the group, along with a clone of
+/// its group
+///
+/// If `mask` is provided, only the entities in that `BitSet` are included. If `extra` is provided,
+/// it is joined along with the storages and its values are included as a third item in each tuple.
+fn join_groups(
+    groups: &Ident,
+    field_names: &[&Ident],
+    fields: &[ComponentField],
+    skipped: &TokenStream,
+    mask: Option<&Ident>,
+    extra: Option<&Ident>,
+) -> TokenStream {
+    let joinables = fields.iter().map(|&ComponentField {ident: field_name, is_optional, ..}| {
         if is_optional {
             quote! {#field_name.maybe()}
         } else {
             quote! {&#field_name}
         }
     });
-    let clones = fields.into_iter().map(|&ComponentField {ident: field_name, is_optional, ..}| {
+    let clones = fields.iter().map(|field| {
+        let field_name = field.ident;
+        if field.is_optional {
+            field.optional_component_into_field(quote! {#field_name.cloned()})
+        } else {
+            field.component_into_field(quote! {Clone::clone(#field_name)})
+        }
+    });
+    let tys = fields.iter().map(|f| f.component_ty());
+    let entities = internal_ident("__entities");
+    let entity = internal_ident("__entity");
+    let (mask, mask_pattern) = match mask {
+        Some(mask) => (quote! {#mask,}, quote! {_,}),
+        None => (quote! {}, quote! {}),
+    };
+    let (extra, extra_pattern, extra_item) = match extra {
+        Some(extra) => (quote! {#extra,}, quote! {#extra,}, quote! {, #extra}),
+        None => (quote! {}, quote! {}, quote! {}),
+    };
+    quote! {
+        use ::specs::{Join, Entities};
+        let ( #entities, #(#field_names),* ) = world.system_data::<( Entities, #(::specs::ReadStorage<#tys>),* )>();
+        let mut #groups = ( #mask #extra &#entities, #(#joinables),* ).join().map(|( #mask_pattern #extra_pattern #entity, #(#field_names),* )| (#entity, Self {
+            #(#field_names : #clones),*
+            #skipped
+        } #extra_item));
+    }
+}
+
+fn first_from_world_method(groups: &Ident, join_groups: &TokenStream, group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    quote! {
+        fn read_first_from_world(world: &#krate::World) -> Option<(::specs::Entity, Self)> {
+            #join_groups
+            #groups.next()
+        }
+
+        fn read_first_from_world_sorted(world: &#krate::World) -> Option<(::specs::Entity, Self)> {
+            #join_groups
+            #groups.min_by_key(|(entity, _)| entity.id())
+        }
+    }
+}
+
+fn find_from_world_method(
+    vis: &Visibility,
+    groups: &Ident,
+    join_groups: &TokenStream,
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let pred = internal_ident("pred");
+    quote! {
+        /// Returns the first entity and its group for which the given predicate returns true
+        ///
+        /// Only entities that have all of the required components in this group are considered.
+        #vis fn find_from_world<F>(world: &#krate::World, #pred: F) -> Option<(::specs::Entity, Self)>
+            where F: Fn(::specs::Entity, &Self) -> bool,
+        {
+            #join_groups
+            #groups.find(|(entity, group)| #pred(*entity, group))
+        }
+    }
+}
+
+fn edit_first_method(
+    vis: &Visibility,
+    field_names: &[&Ident],
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let tys = fields.iter().map(|f| f.component_ty());
+    let arg_tys = fields.iter().map(|field| {
+        let ty = field.component_ty();
+        if field.is_optional {
+            quote! {Option<&mut #ty>}
+        } else {
+            quote! {&mut #ty}
+        }
+    });
+    let joinables = fields.iter().map(|&ComponentField {ident: field_name, is_optional, ..}| {
         if is_optional {
-            quote! {#field_name.cloned()}
+            quote! {(&mut #field_name).maybe()}
         } else {
-            quote! {Clone::clone(#field_name)}
+            quote! {&mut #field_name}
         }
     });
-    let tys = fields.into_iter().map(|f| f.ty);
+    let edit = internal_ident("edit");
+    let entities = internal_ident("__entities");
+    let entity = internal_ident("__entity");
+    let not_registered_panics = not_registered_panics(fields, &[], group_attrs);
+    quote! {
+        /// Calls the given function with mutable references to the components of the first
+        /// entity that has all of the required components in this group. Returns false without
+        /// calling the function if there is no such entity.
+        ///
+        /// The components are modified in place, so nothing needs to be cloned or written back.
+        #vis fn edit_first<F>(world: &mut #krate::World, #edit: F) -> bool
+            where F: FnOnce(::specs::Entity, #(#arg_tys),*),
+        {
+            use ::specs::{Join, Entities};
+            #not_registered_panics
+            #[allow(unused_mut)]
+            let ( #entities, #(mut #field_names),* ) = world.system_data::<( Entities, #(::specs::WriteStorage<#tys>),* )>();
+            match ( &#entities, #(#joinables),* ).join().next() {
+                Some(( #entity, #(#field_names),* )) => {
+                    #edit(#entity, #(#field_names),*);
+                    true
+                },
+                None => false,
+            }
+        }
+    }
+}
+
+fn from_world_filtered_method(
+    vis: &Visibility,
+    groups: &Ident,
+    field_names: &[&Ident],
+    fields: &[ComponentField],
+    skipped: &TokenStream,
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let mask = internal_ident("mask");
+    let join_groups = join_groups(groups, field_names, fields, skipped, Some(&mask), None);
+    quote! {
+        /// Returns every entity in the given mask that has all of the required components in this
+        /// group, along with its group
+        ///
+        /// This is useful for reading the groups of the entities found by another query (e.g. a
+        /// spatial query) that produced a `BitSet`.
+        #vis fn from_world_filtered(world: &#krate::World, #mask: &::specs::BitSet) -> Vec<(::specs::Entity, Self)> {
+            #join_groups
+            #groups.collect()
+        }
+    }
+}
+
+fn join_with_method(
+    vis: &Visibility,
+    groups: &Ident,
+    field_names: &[&Ident],
+    fields: &[ComponentField],
+    skipped: &TokenStream,
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let extra = internal_ident("extra");
+    let join_groups = join_groups(groups, field_names, fields, skipped, None, Some(&extra));
     quote! {
-        fn first_from_world(world: &specs::World) -> Option<(specs::Entity, Self)> {
-            use specs::{Join, Entities};
-            let ( __entities, #(#field_names),* ) = world.system_data::<( Entities, #(specs::ReadStorage<#tys>),* )>();
-            ( &__entities, #(#joinables),* ).join().next().map(|( __entity, #(#field_names),* )| (__entity, Self {
-                #(#field_names : #clones),*
-            }))
+        /// Joins the storages of this group with the given joinable (e.g. a reference to a
+        /// `ReadStorage` or a tuple of them) and returns every entity that has all of the required
+        /// components in this group and a value in `extra`, along with its group and that value
+        ///
+        /// Any storages in `extra` must be fetched from the world immutably since the storages of
+        /// this group are fetched while they are borrowed.
+        #vis fn join_with<J: ::specs::Join>(
+            world: &#krate::World,
+            #extra: J,
+        ) -> Vec<(::specs::Entity, Self, J::Type)> {
+            #join_groups
+            #groups.collect()
         }
     }
 }
 
-fn from_world_method(field_names: &[&Ident], fields: &[ComponentField]) -> TokenStream {
-    let tys = fields.into_iter().map(|f| f.ty);
-    let reads = fields.into_iter().map(|&ComponentField {ident: field_name, ty, is_optional}| {
+fn from_world_method(
+    field_names: &[&Ident],
+    fields: &[ComponentField],
+    skipped: &TokenStream,
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let tys = fields.iter().map(|f| f.component_ty());
+    let reads = fields.iter().map(|field| {
+        let ComponentField {ident: field_name, is_optional, ..} = *field;
+        let ty = field.component_ty();
         if is_optional {
-            quote! {#field_name.get(entity).cloned()}
+            field.optional_component_into_field(quote! {#field_name.get(entity).cloned()})
         } else {
-            let err = format!("expected a {} component to be present", quote!(#ty));
-            quote! {#field_name.get(entity).cloned().expect(#err)}
+            let err = format!("expected a {} component to be present", type_name(ty));
+            field.component_into_field(quote! {#field_name.get(entity).cloned().expect(#err)})
         }
     });
     quote! {
-        fn from_world(world: &specs::World, entity: specs::Entity) -> Self {
-            let ( #(#field_names),* ) = world.system_data::<( #(specs::ReadStorage<#tys>),* )>();
+        fn read_from_world(world: &#krate::World, entity: ::specs::Entity) -> Self {
+            let ( #(#field_names),* ) = world.system_data::<( #(::specs::ReadStorage<#tys>),* )>();
 
             Self {
                 #( #field_names : #reads ),*
+                #skipped
             }
         }
     }
 }
 
-fn create_method(fields: &[ComponentField]) -> TokenStream {
-    let with_comp = fields.into_iter().map(|&ComponentField {ident: field_name, is_optional, ..}| {
+fn from_world_first_missing_method(
+    vis: &Visibility,
+    field_names: &[&Ident],
+    fields: &[ComponentField],
+    skipped: &TokenStream,
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let tys = fields.iter().map(|f| f.component_ty());
+    let reads = fields.iter().map(|field| {
+        let ComponentField {ident: field_name, is_optional, ..} = *field;
         if is_optional {
+            field.optional_component_into_field(quote! {#field_name.get(entity).cloned()})
+        } else {
+            let name = field_name.unraw().to_string();
+            field.component_into_field(quote! {
+                match #field_name.get(entity) {
+                    Some(component) => Clone::clone(component),
+                    None => return Err(#name),
+                }
+            })
+        }
+    });
+    quote! {
+        /// Same as `ComponentGroup::from_world`, but returns the name of the first required field
+        /// (in the order that the fields are declared) whose component is missing instead of
+        /// panicking
+        #vis fn from_world_first_missing(
+            world: &#krate::World,
+            entity: ::specs::Entity,
+        ) -> Result<Self, &'static str> {
+            let ( #(#field_names),* ) = world.system_data::<( #(::specs::ReadStorage<#tys>),* )>();
+
+            Ok(Self {
+                #( #field_names : #reads ),*
+                #skipped
+            })
+        }
+    }
+}
+
+/// Adds every present component of the group to the `specs::Builder` bound to `builder`
+fn with_components(fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    add_components(fields, group_attrs, &|_, component| quote! { builder = builder.with(#component); })
+}
+
+/// Generates statements that add the component of every field of `self` that is present, followed
+/// by every tag, to an entity. `add` generates the statement that adds a single component of the
+/// given type.
+fn add_components(
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+    add: &dyn Fn(&Type, TokenStream) -> TokenStream,
+) -> TokenStream {
+    let add_comp = fields.iter().map(|field| {
+        let ComponentField {ident: field_name, is_optional, ..} = *field;
+        let ty = field.component_ty();
+        unless_skipped(field, if is_optional {
+            let add = add(ty, field.field_into_component(quote! {#field_name}));
             quote! {
                 if let Some(#field_name) = self.#field_name {
-                    builder = builder.with(#field_name);
+                    #add
                 }
             }
         } else {
-            quote! { builder = builder.with(self.#field_name); }
-        }
+            add(ty, field.field_into_component(quote! {self.#field_name}))
+        })
     });
+    let add_tags = group_attrs.tags.iter().map(|tag| add(tag, quote! {<#tag as Default>::default()}));
     quote! {
-        fn create(self, world: &mut specs::World) -> specs::Entity {
-            use specs::{Builder, WorldExt};
+        #( #add_comp )*
+        #( #add_tags )*
+    }
+}
+
+fn create_method(fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let validations = fields.iter().filter_map(|field| {
+        let ComponentField {ident: field_name, is_optional, ref validate_each, ..} = *field;
+        let validate_each = validate_each.as_ref()?;
+        let message = format!("invalid value for the `{}` field: {{}}", field_name.unraw());
+        let check = quote! {
+            if let Err(err) = #validate_each(value) {
+                let err: Box<dyn std::error::Error + Send + Sync> = err.into();
+                panic!(#message, err);
+            }
+        };
+        Some(unless_skipped(field, if is_optional {
+            quote! {
+                if let Some(value) = &self.#field_name {
+                    #check
+                }
+            }
+        } else {
+            quote! {
+                let value = &self.#field_name;
+                #check
+            }
+        }))
+    });
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let deny_empty = deny_empty_check(fields, group_attrs);
+    let null_storage_checks = null_storage_checks(fields, group_attrs);
+    let with_comp = with_components(fields, group_attrs);
+    let after_create = group_attrs.after_create.as_ref().map(|after_create| quote! {
+        #after_create(entity, world);
+    });
+    let krate = group_attrs.crate_path();
+    let tags = group_attrs.tags.iter().collect::<Vec<_>>();
+    let registration_checks = registration_checks(fields, &tags, true, |not_registered| quote! {
+        panic!("{}", #krate::ComponentNotRegistered(#not_registered));
+    });
+    quote! {
+        fn create(self, world: &mut #krate::World) -> ::specs::Entity {
+            use ::specs::{Builder, WorldExt};
+            #registration_checks
+            #null_storage_checks
+            #skip_checks
+            #deny_empty
+            #( { #validations } )*
             #[allow(unused_mut)]
             let mut builder = world.create_entity();
-            #( #with_comp )*
+            #with_comp
+            let entity = builder.build();
+            #after_create
+            entity
+        }
+    }
+}
+
+fn create_on_method(fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let deny_empty = deny_empty_check(fields, group_attrs);
+    let null_storage_checks = null_storage_checks(fields, group_attrs);
+    let validations = validate_fields(fields, &|field_name| quote! {self.#field_name}, false);
+    let tags = group_attrs.tags.iter().collect::<Vec<_>>();
+    let registration_checks = registration_checks(fields, &tags, true, |not_registered| quote! {
+        return Err(::specs::error::Error::Custom(::specs::error::BoxedErr(
+            Box::new(#krate::ComponentNotRegistered(#not_registered)),
+        )));
+    });
+    let inserts = add_components(fields, group_attrs, &|ty, component| quote! {
+        world.write_storage::<#ty>().insert(entity, #component)?;
+    });
+    let after_create = group_attrs.after_create.as_ref().map(|after_create| quote! {
+        #after_create(entity, world);
+    });
+    // The same steps as `create`, but unregistered components, invalid values, and errors from
+    // specs are returned instead of causing a panic
+    let body = group_attrs.map_update_error(quote! {()}, quote! {
+        use ::specs::WorldExt;
+        #registration_checks
+        #null_storage_checks
+        #skip_checks
+        #deny_empty
+        #validations
+        #inserts
+        #after_create
+        Ok(())
+    }, false);
+    quote! {
+        fn create_on(self, world: &mut #krate::World, entity: ::specs::Entity) -> Result<(), Self::UpdateError> {
+            #body
+        }
+    }
+}
+
+/// Generates checks that the storage of every field and every type in `extra_tys` is registered
+/// in `world`. The tokens returned by `not_registered` are run with the name of the first component
+/// type that is not registered.
+///
+/// Fetching an unregistered storage panics deep inside of shred, so this makes it possible to
+/// report the mistake in terms of the group instead.
+///
+/// If `only_present` is true, optional fields are only checked if they are `Some` in `self`, since
+/// their storages are not used otherwise.
+fn registration_checks(
+    fields: &[ComponentField],
+    extra_tys: &[&Type],
+    only_present: bool,
+    not_registered: impl Fn(TokenStream) -> TokenStream,
+) -> TokenStream {
+    let check = |ty: &Type| {
+        let name = type_name(ty);
+        let not_registered = not_registered(quote! {#name});
+        quote! {
+            if world.try_fetch::<::specs::storage::MaskedStorage<#ty>>().is_none() {
+                #not_registered
+            }
+        }
+    };
+    let field_checks = fields.iter().map(|field| {
+        let check = check(field.component_ty());
+        if only_present && field.is_optional {
+            let field_name = field.ident;
+            quote! {
+                if self.#field_name.is_some() {
+                    #check
+                }
+            }
+        } else {
+            check
+        }
+    });
+    let extra_checks = extra_tys.iter().map(|ty| check(ty));
+    quote! {
+        #( #field_checks )*
+        #( #extra_checks )*
+    }
+}
+
+/// Generates statements that bind the write storage of every field to its field name, after
+/// returning a `ComponentNotRegistered` error if any of the storages that will be written is not
+/// registered
+///
+/// Every method that writes the components of a group either fetches its storages through this or
+/// generates the same checks with `not_registered_errors`, so none of them panic because of an
+/// unregistered component.
+pub(crate) fn write_storages(fields: &[ComponentField], group_attrs: &GroupAttrs, tag_errors: bool) -> TokenStream {
+    let not_registered_errors = not_registered_errors(fields, group_attrs.version.as_ref(), group_attrs, tag_errors);
+    let field_names = fields.iter().map(|f| f.ident);
+    let tys = fields.iter().map(|f| f.component_ty());
+    quote! {
+        #not_registered_errors
+        let ( #(mut #field_names),* ) = world.system_data::<( #( ::specs::WriteStorage<#tys> ),* )>();
+    }
+}
+
+/// Generates checks that return a `ComponentNotRegistered` error if the storage of any field or of
+/// the `version` component is not registered
+///
+/// If `tag_errors` is true, the error is returned along with the name of the field whose storage is
+/// not registered, or `"version"` for the version component.
+fn not_registered_errors(
+    fields: &[ComponentField],
+    version: Option<&Type>,
+    group_attrs: &GroupAttrs,
+    tag_errors: bool,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let error = |tag: String, not_registered: TokenStream| {
+        let err = quote! {
+            ::specs::error::Error::Custom(::specs::error::BoxedErr(
+                Box::new(#krate::ComponentNotRegistered(#not_registered)),
+            ))
+        };
+        if tag_errors {
+            quote! { return Err((#tag, #err)); }
+        } else {
+            quote! { return Err(#err); }
+        }
+    };
+    let field_checks = fields.iter().map(|field| {
+        let name = field.ident.unraw().to_string();
+        registration_checks(std::slice::from_ref(field), &[], false, |nr| error(name.clone(), nr))
+    });
+    let version_check = version.map(|version| {
+        registration_checks(&[], &[version], false, |nr| error("version".to_string(), nr))
+    });
+    quote! {
+        #( #field_checks )*
+        #version_check
+    }
+}
+
+/// Generates checks that panic with a `ComponentNotRegistered` message if the storage of any field
+/// or any type in `extra_tys` is not registered, for the methods that write components but cannot
+/// return an error
+pub(crate) fn not_registered_panics(
+    fields: &[ComponentField],
+    extra_tys: &[&Type],
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    registration_checks(fields, extra_tys, false, |not_registered| quote! {
+        panic!("{}", #krate::ComponentNotRegistered(#not_registered));
+    })
+}
+
+/// Generates an assertion that at least one component will be added by `create` if the group has
+/// the `deny_empty` attribute
+///
+/// A group with a required field or a tag can never be empty, so nothing is generated for it.
+fn deny_empty_check(fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    if !group_attrs.deny_empty || !group_attrs.tags.is_empty() || fields.iter().any(|f| !f.is_optional) {
+        return quote! {};
+    }
+
+    let present = fields.iter().map(|field| {
+        let field_name = field.ident;
+        if field.skip_if.is_some() {
+            let flag = skip_flag(field);
+            quote! { (!#flag && self.#field_name.is_some()) }
+        } else {
+            quote! { self.#field_name.is_some() }
+        }
+    });
+    quote! {
+        assert!(false #( || #present )*,
+            "attempted to create an entity without any components because every field of `{}` is None",
+            std::any::type_name::<Self>());
+    }
+}
+
+/// Generates statements that produce a deprecation warning for every field whose component uses
+/// `NullStorage` but is not marked with the `tag` attribute
+///
+/// This can only be detected for components with a concrete type. The tokens are spanned to the
+/// type of each field so the warning points at the field.
+fn null_storage_checks(fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let checks = fields.iter().filter(|field| !field.tag).map(|field| {
+        let ty = field.component_ty();
+        quote_spanned! {ty.span()=>
+            #[allow(clippy::needless_borrow)]
+            {
+                // Only one of the traits is used for each field
+                #[allow(unused_imports)]
+                use #krate::__null_storage::{Check, IsNullStorage, IsOtherStorage};
+                (&Check::<#ty>(std::marker::PhantomData)).check().warn();
+            }
+        }
+    });
+    quote! { #( #checks )* }
+}
+
+fn lazy_build_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let with_comp = with_components(fields, group_attrs);
+    quote! {
+        /// Same as `ComponentGroup::create`, but adds the components to the entity being built by
+        /// the given `LazyBuilder`. The components are only added once the world is maintained.
+        #vis fn lazy_build(self, builder: ::specs::world::LazyBuilder) -> ::specs::Entity {
+            use ::specs::Builder;
+            #skip_checks
+            #[allow(unused_mut)]
+            let mut builder = builder;
+            #with_comp
             builder.build()
         }
     }
 }
 
-fn update_method(field_names: &[&Ident], fields: &[ComponentField]) -> TokenStream {
-    let tys = fields.into_iter().map(|f| f.ty);
-    let updates = fields.into_iter().map(|&ComponentField {ident: field_name, is_optional, ..}| {
-        if is_optional {
+fn extend_builder_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let with_comp = with_components(fields, group_attrs);
+    quote! {
+        /// Adds the components of this group to the entity being built by the given
+        /// `EntityBuilder` and returns the builder without building the entity. This allows
+        /// components from outside of the group to be added to the same entity.
+        #vis fn extend_builder(self, builder: ::specs::EntityBuilder<'_>) -> ::specs::EntityBuilder<'_> {
+            use ::specs::Builder;
+            #skip_checks
+            #[allow(unused_mut)]
+            let mut builder = builder;
+            #with_comp
+            builder
+        }
+    }
+}
+
+fn attach_lazy_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let inserts = fields.iter().map(|field| {
+        let ComponentField {ident: field_name, is_optional, ..} = *field;
+        unless_skipped(field, if is_optional {
+            let component = field.field_into_component(quote! {value});
             quote! {
-                match self.#field_name {
-                    Some(value) => #field_name.insert(entity, value)?,
-                    None => #field_name.remove(entity),
+                if let Some(value) = self.#field_name {
+                    lazy.insert(entity, #component);
+                }
+            }
+        } else {
+            let component = field.field_into_component(quote! {self.#field_name});
+            quote! { lazy.insert(entity, #component); }
+        })
+    });
+    let tags = &group_attrs.tags;
+    quote! {
+        /// Adds the components of this group to an entity that was already allocated (e.g. with
+        /// `Entities::create` in a system). The components are only added once the world is
+        /// maintained.
+        #vis fn attach_lazy(self, entity: ::specs::Entity, lazy: &::specs::LazyUpdate) {
+            #skip_checks
+            #( #inserts )*
+            #( lazy.insert(entity, <#tags as Default>::default()); )*
+        }
+    }
+}
+
+fn create_many_method(fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let interned: Vec<_> = fields.iter().filter(|f| f.intern).collect();
+    if interned.is_empty() {
+        // The default implementation from the trait is sufficient
+        return quote! {};
+    }
+
+    let pools: Vec<_> = interned.iter()
+        .map(|f| internal_ident(&format!("__{}_pool", f.ident.unraw())))
+        .collect();
+    let tys = interned.iter().map(|f| f.ty);
+    let interns = interned.iter().zip(&pools).map(|(&&ComponentField {ident: field_name, is_optional, ..}, pool)| {
+        let intern = quote! {
+            match #pool.get(&value) {
+                Some(existing) => Clone::clone(existing),
+                None => {
+                    #pool.insert(Clone::clone(&value));
+                    value
+                },
+            }
+        };
+        if is_optional {
+            quote! { group.#field_name = group.#field_name.map(|value| #intern); }
+        } else {
+            quote! { group.#field_name = { let value = group.#field_name; #intern }; }
+        }
+    });
+    quote! {
+        fn create_many(world: &mut #krate::World, groups: Vec<Self>) -> Vec<::specs::Entity> {
+            #( let mut #pools = std::collections::HashSet::<#tys>::new(); )*
+            groups.into_iter().map(|mut group| {
+                #( #interns )*
+                group.create(world)
+            }).collect()
+        }
+    }
+}
+
+fn update_method(
+    field_names: &[&Ident],
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+    skipped: &TokenStream,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let many_skip_checks = skip_checks(fields, &quote! {group});
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let write_storages = write_storages(fields, group_attrs, false);
+    let updates = update_fields(fields, group_attrs, &|field_name| quote! {self.#field_name}, false);
+    let updates = quote! { #skip_checks #updates };
+    let many_updates = update_fields(fields, group_attrs, &|field_name| quote! {group.#field_name}, false);
+    let many_updates = quote! { #many_skip_checks #many_updates };
+    // Every component is required to implement Clone, so the group itself does not need to
+    // implement Clone for the fields to be cloned. Without that bound, the default implementation
+    // from the trait is used instead.
+    let set_all = if group_attrs.without_clone {
+        quote! {}
+    } else {
+        let body = group_attrs.map_update_error(quote! {()}, quote! {
+            #write_storages
+
+            for &entity in entities {
+                let group = Self {
+                    #( #field_names : Clone::clone(&self.#field_names) ),*
+                    #skipped
                 };
+                #many_updates
+            }
+
+            Ok(())
+        }, false);
+        quote! {
+            fn set_all(self, world: &mut #krate::World, entities: &[::specs::Entity]) -> Result<(), Self::UpdateError> {
+                #body
+            }
+        }
+    };
+    let update_columns = update_columns(fields, group_attrs, &many_skip_checks);
+    let update_error = group_attrs.update_error();
+    let update = group_attrs.map_update_error(quote! {()}, quote! {
+        #write_storages
+
+        #updates
+
+        Ok(())
+    }, false);
+    let update_many = group_attrs.map_update_error(quote! {()}, quote! {
+        #write_storages
+
+        #update_columns
+
+        for (entity, group) in updates {
+            #many_updates
+        }
+
+        Ok(())
+    }, false);
+    quote! {
+        type UpdateError = #update_error;
+        fn update(self, world: &mut #krate::World, entity: ::specs::Entity) -> Result<(), Self::UpdateError> {
+            #update
+        }
+
+        fn update_many(
+            world: &mut #krate::World,
+            updates: Vec<(::specs::Entity, Self)>,
+        ) -> Result<(), Self::UpdateError> {
+            #update_many
+        }
+
+        #set_all
+    }
+}
+
+/// Generates statements that write every update in `updates` one storage at a time and then
+/// return, so each storage is only traversed once for the entire batch
+///
+/// That is only done if none of the updates can fail, since the batch would otherwise be left
+/// partially written in a different way than updating each entity in order. If any entity is dead
+/// or any value is invalid, the generated code falls through so the updates can be applied one
+/// entity at a time instead.
+fn update_columns(fields: &[ComponentField], group_attrs: &GroupAttrs, skip_checks: &TokenStream) -> TokenStream {
+    let entities_res = internal_ident("__entities_res");
+    let entities = internal_ident("__entities");
+    let value = internal_ident("__value");
+    let infallible = internal_ident("__infallible");
+
+    let valid = fields.iter().filter_map(|field| {
+        let ComponentField {ident: field_name, is_optional, ref validate_each, ..} = *field;
+        let validate_each = validate_each.as_ref()?;
+        let valid = if is_optional {
+            quote! { group.#field_name.as_ref().map_or(true, |value| #validate_each(value).is_ok()) }
+        } else {
+            quote! { #validate_each(&group.#field_name).is_ok() }
+        };
+        Some(match field.skip_if {
+            Some(_) => {
+                let flag = skip_flag(field);
+                quote! { && (#flag || #valid) }
+            },
+            None => quote! { && #valid },
+        })
+    });
+
+    let columns: Vec<_> = fields.iter()
+        .map(|f| internal_ident(&format!("__{}_column", f.ident.unraw())))
+        .collect();
+    let field_names = fields.iter().map(|f| f.ident);
+    let skipped: Vec<_> = fields.iter().filter(|f| f.skip_if.is_some()).collect();
+    let flags: Vec<_> = skipped.iter().map(|f| skip_flag(f)).collect();
+    let flag_columns: Vec<_> = skipped.iter()
+        .map(|f| internal_ident(&format!("__skip_{}_column", f.ident.unraw())))
+        .collect();
+
+    let writes = fields.iter().zip(&columns).map(|(field, column)| {
+        let write = write_field(field, group_attrs, &|_| quote! {#value}, false, None);
+        if field.skip_if.is_some() {
+            let flag = skip_flag(field);
+            let flag_column = internal_ident(&format!("__skip_{}_column", field.ident.unraw()));
+            quote! {
+                for ((&entity, #value), #flag) in #entities.iter().zip(#column).zip(#flag_column) {
+                    #write
+                }
             }
         } else {
-            quote! { #field_name.insert(entity, self.#field_name)?; }
+            quote! {
+                for (&entity, #value) in #entities.iter().zip(#column) {
+                    #write
+                }
+            }
+        }
+    });
+
+    let bump_versions = group_attrs.version.as_ref().map(|_| {
+        let bump_version = bump_version(group_attrs, false);
+        quote! {
+            for entity in #entities {
+                #bump_version
+            }
         }
     });
+
+    quote! {
+        let #entities_res = world.system_data::<::specs::Entities>();
+        let #infallible = updates.iter().all(|(entity, group)| {
+            #skip_checks
+            #entities_res.is_alive(*entity) #( #valid )*
+        });
+        if #infallible {
+            let mut #entities = Vec::with_capacity(updates.len());
+            #( let mut #columns = Vec::with_capacity(updates.len()); )*
+            #( let mut #flag_columns = Vec::with_capacity(updates.len()); )*
+            for (entity, group) in updates {
+                #skip_checks
+                #entities.push(entity);
+                #( #flag_columns.push(#flags); )*
+                #( #columns.push(group.#field_names); )*
+            }
+
+            #( #writes )*
+            #bump_versions
+
+            return Ok(());
+        }
+    }
+}
+
+/// Increments the version component of `entity` if the group has a `version` attribute. A version
+/// component with the default value is inserted first if the entity does not have one yet.
+///
+/// If `tag_errors` is true, any error is returned along with the name `"version"`.
+fn bump_version(group_attrs: &GroupAttrs, tag_errors: bool) -> TokenStream {
+    let version = match group_attrs.version {
+        Some(ref version) => version,
+        None => return quote! {},
+    };
+    let krate = group_attrs.crate_path();
+    let versions = internal_ident("__versions");
+    let map_err = if tag_errors {
+        quote! {.map_err(|err| ("version", err))}
+    } else {
+        quote! {}
+    };
+    quote! {
+        {
+            let mut #versions = world.system_data::<::specs::WriteStorage<#version>>();
+            match #versions.get_mut(entity) {
+                Some(version) => #krate::Increment::increment(version),
+                None => {
+                    let mut version = <#version as Default>::default();
+                    #krate::Increment::increment(&mut version);
+                    #versions.insert(entity, version) #map_err ?;
+                },
+            }
+        }
+    }
+}
+
+/// Writes the value of every field into the storages bound to each field name for `entity`
+///
+/// The expression for the value of each field is returned by `field_value` (e.g. `group.field`).
+/// If `tag_errors` is true, any error is returned along with the name of the field that caused it.
+fn update_fields(
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+    field_value: &dyn Fn(&Ident) -> TokenStream,
+    tag_errors: bool,
+) -> TokenStream {
+    let validations = validate_fields(fields, field_value, tag_errors);
+    let updates = write_fields(fields, group_attrs, field_value, tag_errors, None);
+    // Every field is validated before anything is written so that nothing is written if any
+    // value is invalid
     quote! {
-        type UpdateError = specs::error::Error;
-        fn update(self, world: &mut specs::World, entity: specs::Entity) -> Result<(), Self::UpdateError> {
-            let ( #(mut #field_names),* ) = world.system_data::<( #( specs::WriteStorage<#tys> ),* )>();
+        #validations
+        #updates
+    }
+}
 
-            #( #updates )*
+/// Same as `update_fields`, but without validating the values first
+///
+/// The version of `entity` is bumped once after every field is written, so every method that
+/// writes a group through this function keeps the version up to date. Methods that write their
+/// fields one at a time with `write_field` need to call `bump_version` themselves.
+///
+/// If `removed` is provided, the name of every field whose component was removed because the field
+/// was None is pushed onto the `Vec` bound to that identifier.
+fn write_fields(
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+    field_value: &dyn Fn(&Ident) -> TokenStream,
+    tag_errors: bool,
+    removed: Option<&Ident>,
+) -> TokenStream {
+    let updates = fields.iter()
+        .map(|field| write_field(field, group_attrs, field_value, tag_errors, removed));
+    let bump_version = bump_version(group_attrs, tag_errors);
+    quote! {
+        #( #updates )*
+        #bump_version
+    }
+}
 
+/// Writes the value of a single field into the storage bound to its field name for `entity`,
+/// without bumping the version
+fn write_field(
+    field: &ComponentField,
+    group_attrs: &GroupAttrs,
+    field_value: &dyn Fn(&Ident) -> TokenStream,
+    tag_errors: bool,
+    removed: Option<&Ident>,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let ComponentField {ident: field_name, is_optional, ..} = *field;
+    let ty = field.component_ty();
+    let map_err = if tag_errors {
+        let name = field_name.unraw().to_string();
+        quote! {.map_err(|err| (#name, err))}
+    } else {
+        quote! {}
+    };
+
+    let value = field_value(field_name);
+    if is_optional {
+        let update = update_value(field_name, field.field_into_component(quote! {value}), &map_err);
+        let warn = if group_attrs.warn_on_remove {
+            let message = format!("removing the {} component from entity {{:?}} because the \
+                `{}` field is None", type_name(ty), field_name.unraw());
+            Some(quote! { #krate::log::warn!(#message, entity); })
+        } else {
+            None
+        };
+        let record = removed.map(|removed| {
+            let name = field_name.unraw().to_string();
+            quote! { #removed.push(#name); }
+        });
+        let remove = if warn.is_some() || record.is_some() {
+            quote! {
+                if #field_name.remove(entity).is_some() {
+                    #warn
+                    #record
+                }
+            }
+        } else {
+            quote! { #field_name.remove(entity); }
+        };
+        unless_skipped(field, quote! {
+            match #value {
+                Some(value) => #update,
+                None => { #remove },
+            }
+        })
+    } else {
+        unless_skipped(field, update_value(field_name, field.field_into_component(value), &map_err))
+    }
+}
+
+/// Calls the `validate_each` function of every field that has one with a reference to the value
+/// returned by `field_value`, returning early with any error converted to a specs error
+///
+/// If `tag_errors` is true, any error is returned along with the name of the field that caused it
+fn validate_fields(
+    fields: &[ComponentField],
+    field_value: &dyn Fn(&Ident) -> TokenStream,
+    tag_errors: bool,
+) -> TokenStream {
+    let validations = fields.iter().filter_map(|field| {
+        let ComponentField {ident: field_name, is_optional, ref validate_each, ..} = *field;
+        let validate_each = validate_each.as_ref()?;
+        let value = field_value(field_name);
+        let map_err = if tag_errors {
+            let name = field_name.unraw().to_string();
+            quote! {.map_err(|err| (#name, ::specs::error::Error::Custom(::specs::error::BoxedErr(err.into()))))}
+        } else {
+            quote! {.map_err(|err| ::specs::error::Error::Custom(::specs::error::BoxedErr(err.into())))}
+        };
+
+        Some(unless_skipped(field, if is_optional {
+            quote! {
+                if let Some(value) = &#value {
+                    #validate_each(value) #map_err ?;
+                }
+            }
+        } else {
+            quote! { #validate_each(&#value) #map_err ?; }
+        }))
+    });
+    quote! { #( #validations )* }
+}
+
+/// Returns the identifier of the flag that is true if the given field should not be written
+fn skip_flag(field: &ComponentField) -> Ident {
+    internal_ident(&format!("__skip_{}", field.ident.unraw()))
+}
+
+/// Generates a statement that binds the skip flag of every field with a `skip_if` function to the
+/// result of calling that function with a reference to `group`
+///
+/// The flags must be bound before any field of the group is moved out.
+fn skip_checks(fields: &[ComponentField], group: &TokenStream) -> TokenStream {
+    let checks = fields.iter().filter_map(|field| {
+        let skip_if = field.skip_if.as_ref()?;
+        let flag = skip_flag(field);
+        Some(quote! { let #flag = #skip_if(&#group); })
+    });
+    quote! { #( #checks )* }
+}
+
+/// Wraps the given tokens so they only run if the field is not skipped by its `skip_if` function.
+/// The skip flags must have been bound with `skip_checks`.
+fn unless_skipped(field: &ComponentField, tokens: TokenStream) -> TokenStream {
+    if field.skip_if.is_none() {
+        return tokens;
+    }
+    let flag = skip_flag(field);
+    quote! {
+        if !#flag {
+            #tokens
+        }
+    }
+}
+
+/// Writes a value into the storage bound to `storage` for `entity`
+///
+/// Any error from `insert` is passed through `map_err` before being returned. specs already
+/// overwrites an existing component in place when it is inserted again, so the same method is
+/// used whether or not the entity has the component.
+fn update_value(storage: &Ident, value: TokenStream, map_err: &TokenStream) -> TokenStream {
+    quote! {
+        { #storage.insert(entity, #value) #map_err ?; }
+    }
+}
+
+fn json_methods(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs, skipped: &TokenStream) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident).collect();
+    let names: Vec<_> = field_names.iter().map(|field_name| field_name.unraw().to_string()).collect();
+    let tys: Vec<_> = fields.iter().map(|f| f.ty).collect();
+    let object = internal_ident("__object");
+    let values = fields.iter().zip(&names).map(|(field, name)| {
+        let missing = if field.is_optional {
+            quote! { None }
+        } else {
+            quote! { return Err(<#krate::serde_json::Error as #krate::serde::de::Error>::missing_field(#name)) }
+        };
+        quote! {
+            match #object.get(#name) {
+                Some(value) => #krate::serde_json::from_value(Clone::clone(value))?,
+                None => #missing,
+            }
+        }
+    });
+    let to_json_messages = names.iter().map(|name| format!("unable to convert the `{}` field to JSON", name));
+    let serialize_bounds = tys.iter().map(|ty| deferred_bound(ty, quote! {#krate::serde::Serialize}));
+    let deserialize_bounds = tys.iter().map(|ty| deferred_bound(ty, quote! {#krate::serde::de::DeserializeOwned}));
+
+    quote! {
+        #krate::__serde_json! {
+            /// Converts this group into a JSON object with a key for the value of each field
+            #vis fn to_json(&self) -> #krate::serde_json::Value
+                where #( #serialize_bounds ),*
+            {
+                let mut #object = #krate::serde_json::Map::new();
+                #(
+                    #object.insert(
+                        String::from(#names),
+                        #krate::serde_json::to_value(&self.#field_names).expect(#to_json_messages),
+                    );
+                )*
+                #krate::serde_json::Value::Object(#object)
+            }
+
+            /// Creates a group from a JSON object with a key for the value of each field, like the
+            /// one returned by `to_json`. Optional fields that are missing from the object are set
+            /// to None.
+            #vis fn from_json(value: &#krate::serde_json::Value) -> Result<Self, #krate::serde_json::Error>
+                where #( #deserialize_bounds ),*
+            {
+                let #object = match value.as_object() {
+                    Some(object) => object,
+                    None => return Err(<#krate::serde_json::Error as #krate::serde::de::Error>::custom("expected a JSON object")),
+                };
+                Ok(Self {
+                    #( #field_names : #values ),*
+                    #skipped
+                })
+            }
+        }
+    }
+}
+
+fn par_set_all_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let field_names = fields.iter().map(|f| f.ident);
+    let tys = fields.iter().map(|f| f.component_ty());
+    let (required, optional): (Vec<ComponentField>, Vec<ComponentField>) = fields.iter().cloned()
+        .partition(|f| !f.is_optional);
+    let required_names: Vec<_> = required.iter().map(|f| f.ident).collect();
+    let distinct_bounds = required.iter().map(|field| {
+        let ty = field.component_ty();
+        deferred_bound(quote! {<#ty as ::specs::Component>::Storage}, quote! {Sync + ::specs::storage::DistinctStorage})
+    });
+
+    let entities = internal_ident("__entities");
+    let matching = internal_ident("__matching");
+    let find_matching = if required_names.is_empty() {
+        quote! { (&#entities).join().collect() }
+    } else {
+        quote! { ( &#entities, #(&#required_names),* ).join().map(|(entity, ..)| entity).collect() }
+    };
+
+    // The closure only captures references to the values so that the group does not need to be Sync
+    let values: Vec<_> = required_names.iter()
+        .map(|field_name| internal_ident(&format!("__{}_value", field_name.unraw())))
+        .collect();
+    let set_required_fields = required.iter().zip(&values).map(|(field, value)| {
+        let field_name = field.ident;
+        let component = field.field_into_component(quote! {Clone::clone(#value)});
+        unless_skipped(field, quote! { *#field_name = #component; })
+    });
+    let set_required = if required_names.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #( let #values = &self.#required_names; )*
+            ( #(&mut #required_names,)* ).par_join().for_each(|( #(#required_names,)* )| {
+                #( #set_required_fields )*
+            });
+        }
+    };
+    let not_registered_errors = not_registered_errors(fields, group_attrs.version.as_ref(), group_attrs, false);
+    let skip_checks = skip_checks(fields, &quote! {self});
+    // Every field is validated before any required components are overwritten
+    let validate_all = validate_fields(fields, &|field_name| quote! {self.#field_name}, false);
+    let set_optional = write_fields(&optional, group_attrs,
+        &|field_name| quote! {Clone::clone(&self.#field_name)}, false, None);
+
+    let body = group_attrs.map_update_error(quote! {()}, quote! {
+        #[allow(unused_imports)]
+        use ::specs::{Join, ParJoin, Entities, rayon::iter::ParallelIterator};
+        #not_registered_errors
+        #[allow(unused_mut)]
+        let ( #entities, #(mut #field_names),* ) = world.system_data::<( Entities, #(::specs::WriteStorage<#tys>),* )>();
+        // Optional components can only be inserted one entity at a time, so the matching
+        // entities are found before any required components are modified
+        let #matching: Vec<::specs::Entity> = #find_matching;
+
+        #skip_checks
+        #validate_all
+        #set_required
+
+        for &entity in &#matching {
+            #set_optional
+        }
+
+        Ok(())
+    }, false);
+    quote! {
+        #krate::__parallel! {
+            /// Sets the components of every entity that has all of the required components in this
+            /// group to clones of the components from this group
+            ///
+            /// The required components are written in parallel. Optional components are then
+            /// inserted or removed one entity at a time. Unlike the other methods, the components
+            /// are not written in the order that the fields are declared.
+            ///
+            /// Can only be called if the storage of every required component supports parallel
+            /// joins (i.e. implements `DistinctStorage`).
+            #vis fn par_set_all(
+                self,
+                world: &mut #krate::World,
+            ) -> Result<(), <Self as #krate::ComponentGroup>::UpdateError>
+                where #( #distinct_bounds ),*
+            {
+                #body
+            }
+        }
+    }
+}
+
+fn remove_with_extras_method(vis: &Visibility, group_attrs: &GroupAttrs) -> TokenStream {
+    if group_attrs.extras.is_empty() {
+        return quote! {};
+    }
+
+    let krate = group_attrs.crate_path();
+    let extras_tys = &group_attrs.extras;
+    let extras = internal_ident("__extras");
+    quote! {
+        /// Same as `ComponentGroup::remove`, but also removes the components listed in the
+        /// `capture_extras` attribute so they can be added back to an entity later
+        #vis fn remove_with_extras(world: &mut #krate::World, entity: ::specs::Entity) -> (Self, #krate::ExtraComponents) {
+            let group = <Self as #krate::ComponentGroup>::remove(world, entity);
+            let mut #extras = #krate::ExtraComponents::new();
+            #( #extras.capture::<#extras_tys>(world, entity); )*
+            (group, #extras)
+        }
+    }
+}
+
+fn remove_audited_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let removed = internal_ident("__removed");
+    // The components that are present are recorded before anything is removed
+    let checks = fields.iter().map(|field| {
+        let name = field.ident.unraw().to_string();
+        let ty = field.component_ty();
+        quote! {
+            if world.read_storage::<#ty>().contains(entity) {
+                #removed.push(#name);
+            }
+        }
+    });
+    let not_registered_panics = not_registered_panics(fields, &[], group_attrs);
+    quote! {
+        /// Same as `ComponentGroup::remove`, but also returns the names of the fields whose
+        /// components were present and removed, in the order that the fields are declared
+        ///
+        /// Optional fields whose components were already absent are not included.
+        #vis fn remove_audited(world: &mut #krate::World, entity: ::specs::Entity) -> (Self, Vec<&'static str>) {
+            use ::specs::WorldExt;
+            #not_registered_panics
+            let mut #removed = Vec::new();
+            #( #checks )*
+            let group = <Self as #krate::ComponentGroup>::remove(world, entity);
+            (group, #removed)
+        }
+    }
+}
+
+fn clear_optionals_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let optional: Vec<_> = fields.iter().filter(|f| f.is_optional).cloned().collect();
+    let optional_tys = optional.iter().map(|f| f.component_ty());
+    let not_registered_errors = not_registered_errors(&optional, None, group_attrs, false);
+    let entities = internal_ident("__entities");
+    let body = group_attrs.map_update_error(quote! {()}, quote! {
+        use ::specs::WorldExt;
+        #not_registered_errors
+        {
+            let #entities = world.system_data::<::specs::Entities>();
+            if !#entities.is_alive(entity) {
+                return Err(::specs::error::WrongGeneration {
+                    action: "clear optional components",
+                    actual_gen: #entities.entity(entity.id()).gen(),
+                    entity,
+                }.into());
+            }
+        }
+        #( world.write_storage::<#optional_tys>().remove(entity); )*
+        Ok(())
+    }, false);
+    quote! {
+        /// Removes the components of every optional field in this group from the given entity,
+        /// leaving the components of the required fields untouched
+        ///
+        /// Returns an error if the entity is no longer alive.
+        #vis fn clear_optionals(
+            world: &mut #krate::World,
+            entity: ::specs::Entity,
+        ) -> Result<(), <Self as #krate::ComponentGroup>::UpdateError> {
+            #body
+        }
+    }
+}
+
+/// Generates a `copy_<field>` method for each field that copies only the component of that field
+/// from one entity to another
+fn copy_methods(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let methods = fields.iter().map(|field| {
+        let field_name = field.ident;
+        let ty = field.component_ty();
+        let method = format_ident!("copy_{}", field_name);
+        let (missing, missing_doc) = if field.is_optional {
+            (quote! {
+                storage.remove(to);
+            }, "If `from` does not have the component, it is removed from `to`.")
+        } else {
+            let err = format!("expected a {} component to be present", type_name(ty));
+            (quote! {
+                panic!(#err);
+            }, "Panics if `from` does not have the component.")
+        };
+        let not_registered_errors = not_registered_errors(std::slice::from_ref(field),
+            group_attrs.version.as_ref(), group_attrs, false);
+        let bump_version = bump_version(group_attrs, false);
+        let body = group_attrs.map_update_error(quote! {()}, quote! {
+            use ::specs::WorldExt;
+            #not_registered_errors
+            let mut storage = world.write_storage::<#ty>();
+            match storage.get(from).cloned() {
+                Some(value) => {
+                    storage.insert(to, value)?;
+                },
+                None => {
+                    #missing
+                },
+            }
+            let entity = to;
+            #bump_version
             Ok(())
+        }, false);
+        let doc = format!("Copies the component of the `{}` field from the entity `from` to the \
+            entity `to`, leaving the rest of the components of `to` untouched", field_name.unraw());
+        quote! {
+            #[doc = #doc]
+            ///
+            #[doc = #missing_doc]
+            #vis fn #method(
+                world: &mut #krate::World,
+                from: ::specs::Entity,
+                to: ::specs::Entity,
+            ) -> Result<(), <Self as #krate::ComponentGroup>::UpdateError> {
+                #body
+            }
+        }
+    });
+    quote! { #( #methods )* }
+}
+
+fn take_all_from_world_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let (required, optional): (Vec<&ComponentField>, Vec<&ComponentField>) = fields.iter()
+        .partition(|f| !f.is_optional);
+    let not_registered_panics = not_registered_panics(fields, &[], group_attrs);
+    let entities = internal_ident("__entities");
+    let matching = internal_ident("__matching");
+    let find_matching = if required.is_empty() {
+        // Every entity would match if there are no required components, so only the entities
+        // that have at least one of the optional components are included
+        let optional_names = optional.iter().map(|f| f.ident);
+        let optional_tys = optional.iter().map(|f| f.component_ty());
+        let optional_storages: Vec<_> = optional.iter().map(|f| f.ident).collect();
+        let has_any = if optional_storages.is_empty() {
+            quote! { false }
+        } else {
+            quote! { #( #optional_storages.contains(entity) )||* }
+        };
+        quote! {
+            let ( #entities, #(#optional_names),* ) = world.system_data::<( Entities, #(::specs::ReadStorage<#optional_tys>),* )>();
+            (&#entities).join().filter(|&entity| #has_any).collect()
+        }
+    } else {
+        let required_names = required.iter().map(|f| f.ident);
+        let required_storages = required.iter().map(|f| f.ident);
+        let required_tys = required.iter().map(|f| f.component_ty());
+        quote! {
+            let ( #entities, #(#required_names),* ) = world.system_data::<( Entities, #(::specs::ReadStorage<#required_tys>),* )>();
+            ( &#entities #(, &#required_storages)* , ).join().map(|(entity, ..)| entity).collect()
+        }
+    };
+    quote! {
+        /// Removes this group from every entity that has all of the required components in the
+        /// group and returns each entity along with the group that was removed from it
+        ///
+        /// If every field of the group is optional, only the entities that have at least one of
+        /// the components in the group are included.
+        #vis fn take_all_from_world(world: &mut #krate::World) -> Vec<(::specs::Entity, Self)> {
+            #not_registered_panics
+            // The entities are collected before anything is removed so that the storages are not
+            // modified while they are being joined over
+            let #matching: Vec<::specs::Entity> = {
+                use ::specs::{Join, Entities};
+                #find_matching
+            };
+            #matching.into_iter()
+                .map(|entity| (entity, <Self as #krate::ComponentGroup>::remove(world, entity)))
+                .collect()
         }
     }
 }
 
-fn remove_method(field_names: &[&Ident], fields: &[ComponentField]) -> TokenStream {
-    let tys = fields.into_iter().map(|f| f.ty);
-    let reads = fields.into_iter().map(|&ComponentField {ident: field_name, ty, is_optional}| {
-        if is_optional {
-            quote! {#field_name.remove(entity)}
+fn setup_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let tys: Vec<_> = fields.iter().map(|f| f.component_ty()).collect();
+    let tags = &group_attrs.tags;
+    let extras = &group_attrs.extras;
+    let version = group_attrs.version.iter();
+    let default_bounds = tys.iter().map(|ty| deferred_bound(quote! {<#ty as ::specs::Component>::Storage}, quote! {Default}));
+    quote! {
+        /// Registers the storage of every component used by this group, including the optional
+        /// ones and any components added by the group attributes, so that none of the generated
+        /// methods panic because a storage was never registered
+        ///
+        /// Components that are already registered are left untouched, so this is safe to call
+        /// more than once.
+        #vis fn setup(world: &mut #krate::World)
+            where #( #default_bounds ),*
+        {
+            use ::specs::WorldExt;
+            #( world.register::<#tys>(); )*
+            #( world.register::<#tags>(); )*
+            #( world.register::<#extras>(); )*
+            #( world.register::<#version>(); )*
+        }
+    }
+}
+
+fn remap_entities_method(group_attrs: &GroupAttrs) -> TokenStream {
+    match group_attrs.remap_entities {
+        Some(ref remap_entities) => quote! {
+            fn remap_entities(
+                &mut self,
+                entity_map: &std::collections::HashMap<::specs::Entity, ::specs::Entity>,
+            ) {
+                #remap_entities(self, entity_map)
+            }
+        },
+        // The default implementation from the trait does nothing
+        None => quote! {},
+    }
+}
+
+fn update_selected_method(
+    vis: &Visibility,
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+    fields_ident: &Ident,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let write_storages = write_storages(fields, group_attrs, false);
+    let selected = internal_ident("selected");
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let field_value = |field_name: &Ident| quote! {self.#field_name};
+    let validations = fields.iter().filter_map(|field| {
+        let field_name = field.ident;
+        let validation = validate_fields(std::slice::from_ref(field), &field_value, false);
+        if validation.is_empty() {
+            return None;
+        }
+        Some(quote! {
+            if #selected.#field_name {
+                #validation
+            }
+        })
+    });
+    let updates = fields.iter().map(|field| {
+        let field_name = field.ident;
+        let update = write_field(field, group_attrs, &field_value, false, None);
+        quote! {
+            if #selected.#field_name {
+                #update
+            }
+        }
+    });
+    let bump_version = bump_version(group_attrs, false);
+    let doc = format!("Same as `ComponentGroup::update`, but only writes the fields that are \
+        selected in the given `{}`. The components of every other field are left untouched.",
+        fields_ident);
+    let body = group_attrs.map_update_error(quote! {()}, quote! {
+        #write_storages
+
+        #skip_checks
+        #( #validations )*
+        #( #updates )*
+        #bump_version
+
+        Ok(())
+    }, false);
+    quote! {
+        #[doc = #doc]
+        #vis fn update_selected(
+            self,
+            world: &mut #krate::World,
+            entity: ::specs::Entity,
+            #selected: #fields_ident,
+        ) -> Result<(), <Self as #krate::ComponentGroup>::UpdateError> {
+            #body
+        }
+    }
+}
+
+fn try_update_method(
+    vis: &Visibility,
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let write_storages = write_storages(fields, group_attrs, true);
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let updates = update_fields(fields, group_attrs, &|field_name| quote! {self.#field_name}, true);
+    let body = group_attrs.map_update_error(quote! {()}, quote! {
+        #write_storages
+
+        #skip_checks
+        #updates
+
+        Ok(())
+    }, true);
+    quote! {
+        /// Same as `ComponentGroup::update`, but any error is returned along with the name of the
+        /// field whose component could not be written
+        #vis fn try_update(
+            self,
+            world: &mut #krate::World,
+            entity: ::specs::Entity,
+        ) -> Result<(), (&'static str, <Self as #krate::ComponentGroup>::UpdateError)> {
+            #body
+        }
+    }
+}
+
+fn update_returning_removed_method(
+    vis: &Visibility,
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let write_storages = write_storages(fields, group_attrs, false);
+    let removed = internal_ident("__removed");
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let field_value = |field_name: &Ident| quote! {self.#field_name};
+    let validations = validate_fields(fields, &field_value, false);
+    let updates = write_fields(fields, group_attrs, &field_value, false, Some(&removed));
+    let body = group_attrs.map_update_error(quote! {Vec<&'static str>}, quote! {
+        #write_storages
+        #[allow(unused_mut)]
+        let mut #removed = Vec::new();
+
+        #skip_checks
+        #validations
+        #updates
+
+        Ok(#removed)
+    }, false);
+    quote! {
+        /// Same as `ComponentGroup::update`, but returns the names of the optional fields whose
+        /// components were removed because the field was `None`
+        ///
+        /// Fields whose components were already absent are not included.
+        #vis fn update_returning_removed(
+            self,
+            world: &mut #krate::World,
+            entity: ::specs::Entity,
+        ) -> Result<Vec<&'static str>, <Self as #krate::ComponentGroup>::UpdateError> {
+            #body
+        }
+    }
+}
+
+fn update_with_events_method(
+    vis: &Visibility,
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let write_storages = write_storages(fields, group_attrs, false);
+    let events = internal_ident("__events");
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let field_value = |field_name: &Ident| quote! {self.#field_name};
+    let validations = validate_fields(fields, &field_value, false);
+    let updates = write_fields(fields, group_attrs, &field_value, false, None);
+    // The events are determined from the state of the storages before anything is written
+    let classify = fields.iter().map(|field| {
+        let field_name = field.ident;
+        let name = field_name.unraw().to_string();
+        let event = |kind: &str| {
+            let kind = Ident::new(kind, Span::call_site());
+            quote! { #events.push((#name, #krate::EventKind::#kind)) }
+        };
+        let (inserted, modified, removed) = (event("Inserted"), event("Modified"), event("Removed"));
+        unless_skipped(field, if field.is_optional {
+            quote! {
+                if self.#field_name.is_some() {
+                    if #field_name.contains(entity) { #modified; } else { #inserted; }
+                } else if #field_name.contains(entity) {
+                    #removed;
+                }
+            }
+        } else {
+            quote! {
+                if #field_name.contains(entity) { #modified; } else { #inserted; }
+            }
+        })
+    });
+    let body = group_attrs.map_update_error(quote! {Vec<(&'static str, #krate::EventKind)>}, quote! {
+        #write_storages
+        #[allow(unused_mut)]
+        let mut #events = Vec::new();
+
+        #skip_checks
+        #validations
+        #( #classify )*
+        #updates
+
+        Ok(#events)
+    }, false);
+    quote! {
+        /// Same as `ComponentGroup::update`, but returns the kind of storage event that writing
+        /// each field produced, in the order that the fields are declared
+        ///
+        /// Optional fields that were `None` and whose components were already absent are not
+        /// included since nothing was written for them.
+        #vis fn update_with_events(
+            self,
+            world: &mut #krate::World,
+            entity: ::specs::Entity,
+        ) -> Result<Vec<(&'static str, #krate::EventKind)>, <Self as #krate::ComponentGroup>::UpdateError> {
+            #body
+        }
+    }
+}
+
+fn update_stats_method(
+    vis: &Visibility,
+    fields: &[ComponentField],
+    group_attrs: &GroupAttrs,
+) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let eq_bounds = fields.iter().map(|field| deferred_bound(field.component_ty(), quote! {PartialEq}));
+    let write_storages = write_storages(fields, group_attrs, false);
+    let stats = internal_ident("__stats");
+    let skip_checks = skip_checks(fields, &quote! {self});
+    let field_value = |field_name: &Ident| quote! {self.#field_name};
+    let validations = validate_fields(fields, &field_value, false);
+    let updates = write_fields(fields, group_attrs, &field_value, false, None);
+    // The stats are determined from the state of the storages before anything is written
+    let classify = fields.iter().map(|field| {
+        let field_name = field.ident;
+        let value = internal_ident("__value");
+        let existing = internal_ident("__existing");
+        let new_value = if field.component.is_some() {
+            let converted = field.field_into_component(quote! {Clone::clone(#value)});
+            quote! {&#converted}
+        } else {
+            quote! {#value}
+        };
+        let count = quote! {
+            match #field_name.get(entity) {
+                Some(#existing) => if #existing != #new_value { #stats.modified += 1; },
+                None => #stats.inserted += 1,
+            }
+        };
+        unless_skipped(field, if field.is_optional {
+            quote! {
+                if let Some(#value) = &self.#field_name {
+                    #count
+                } else if #field_name.contains(entity) {
+                    #stats.removed += 1;
+                }
+            }
+        } else {
+            quote! {
+                let #value = &self.#field_name;
+                #count
+            }
+        })
+    });
+    let body = group_attrs.map_update_error(quote! {#krate::UpdateStats}, quote! {
+        #write_storages
+        let mut #stats = #krate::UpdateStats::default();
+
+        #skip_checks
+        #validations
+        #( #classify )*
+        #updates
+
+        Ok(#stats)
+    }, false);
+    quote! {
+        /// Same as `ComponentGroup::update`, but returns the number of components that were
+        /// inserted, modified, and removed
+        ///
+        /// A component only counts as modified if its new value is not equal to the value that
+        /// was already in the world, so every component must implement `PartialEq`.
+        #vis fn update_stats(
+            self,
+            world: &mut #krate::World,
+            entity: ::specs::Entity,
+        ) -> Result<#krate::UpdateStats, <Self as #krate::ComponentGroup>::UpdateError>
+            where #( #eq_bounds ),*
+        {
+            #body
+        }
+    }
+}
+
+fn component_names_present_method(fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let checks = fields.iter().map(|field| {
+        let ty = field.component_ty();
+        let name = type_name(ty);
+        quote! {
+            if world.read_storage::<#ty>().contains(entity) {
+                names.push(#name);
+            }
+        }
+    });
+    quote! {
+        fn component_names_present(world: &#krate::World, entity: ::specs::Entity) -> Vec<&'static str> {
+            use ::specs::WorldExt;
+            let mut names = Vec::new();
+            #( #checks )*
+            names
+        }
+    }
+}
+
+fn matches_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let required_tys = fields.iter().filter(|f| !f.is_optional).map(|f| f.component_ty());
+    quote! {
+        /// Returns true if the given entity has every required component in this group
+        #vis fn matches(world: &#krate::World, entity: ::specs::Entity) -> bool {
+            use ::specs::WorldExt;
+            // Each storage is only fetched once the checks for all the fields before it have
+            // passed, so the first missing component stops the search without touching the
+            // storages of the remaining fields
+            true #( && world.read_storage::<#required_tys>().contains(entity) )*
+        }
+    }
+}
+
+fn field_names_methods(vis: &Visibility, fields: &[ComponentField]) -> TokenStream {
+    let field_names_where = |optional| fields.iter()
+        .filter(move |f| f.is_optional == optional)
+        .map(|f| f.ident.unraw().to_string());
+    let required = field_names_where(false);
+    let optional = field_names_where(true);
+    let names: Vec<_> = fields.iter().map(|f| f.ident.unraw().to_string()).collect();
+    let is_optional: Vec<_> = fields.iter().map(|f| f.is_optional).collect();
+    let field_count = fields.len();
+    let type_names = fields.iter().map(|field| type_name(field.component_ty()));
+    quote! {
+        /// Whether the component of each field is optional, in the order that the fields are
+        /// declared
+        #vis const FIELD_OPTIONAL: [bool; #field_count] = [ #(#is_optional),* ];
+
+        /// Returns the names of the fields in this group whose components are required
+        #vis fn required_field_names() -> &'static [&'static str] {
+            &[ #(#required),* ]
+        }
+
+        /// Returns the names of the fields in this group whose components are optional
+        #vis fn optional_field_names() -> &'static [&'static str] {
+            &[ #(#optional),* ]
+        }
+
+        /// Returns the name of the component type of the field with the given name, or `None` if
+        /// there is no field with that name in this group
+        #vis fn field_type_name(name: &str) -> Option<&'static str> {
+            match name {
+                #( #names => Some(#type_names), )*
+                _ => None,
+            }
+        }
+
+        /// Returns whether the component of the field with the given name is optional, or `None`
+        /// if there is no field with that name in this group
+        #vis fn field_is_optional(name: &str) -> Option<bool> {
+            match name {
+                #( #names => Some(#is_optional), )*
+                _ => None,
+            }
+        }
+    }
+}
+
+fn assert_matches_world_method(vis: &Visibility, fields: &[ComponentField], group_attrs: &GroupAttrs) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let eq_debug_bounds = fields.iter().map(|field| deferred_bound(field.ty, quote! {PartialEq + std::fmt::Debug}));
+    let checks = fields.iter().map(|field| {
+        let ComponentField {ident: field_name, is_optional, ..} = *field;
+        let ty = field.component_ty();
+        let found = if field.component.is_some() || field.read_with.is_some() {
+            // The component is converted into the field type so the two can be compared
+            let converted = field.optional_component_into_field(quote! {storage.get(entity).cloned()});
+            quote! {
+                let found = #converted;
+                let found = found.as_ref();
+            }
         } else {
-            let err = format!("expected a {} component to be present", quote!(#ty));
-            quote! {#field_name.remove(entity).expect(#err)}
+            quote! { let found = storage.get(entity); }
+        };
+        let expected = if is_optional {
+            quote! {self.#field_name.as_ref()}
+        } else {
+            quote! {Some(&self.#field_name)}
+        };
+        let message = format!("the `{}` field does not match the world for entity {{:?}}\n  \
+            expected: {{:?}}\n     found: {{:?}}", field_name.unraw());
+        quote! {
+            {
+                let storage = world.read_storage::<#ty>();
+                let expected = #expected;
+                #found
+                if expected != found {
+                    panic!(#message, entity, expected, found);
+                }
+            }
         }
     });
     quote! {
-        fn remove(world: &mut specs::World, entity: specs::Entity) -> Self {
-            let ( #(mut #field_names),* ) = world.system_data::<( #(specs::WriteStorage<#tys>),* )>();
+        /// Panics with a message describing the first field whose value differs from the
+        /// component of the given entity in the world
+        ///
+        /// Only available with debug assertions enabled. Requires every component to implement
+        /// `PartialEq` and `Debug`.
+        #[cfg(debug_assertions)]
+        #vis fn assert_matches_world(&self, world: &#krate::World, entity: ::specs::Entity)
+            where #( #eq_debug_bounds ),*
+        {
+            use ::specs::WorldExt;
+            #( #checks )*
+        }
+    }
+}
+
+fn remove_method(fields: &[ComponentField], group_attrs: &GroupAttrs, skipped: &TokenStream) -> TokenStream {
+    let krate = group_attrs.crate_path();
+    let field_names = fields.iter().map(|f| f.ident);
+    // Storage::remove only returns Some(...) if the component was in the storage's mask. Storages
+    // that leave a value behind after removal (e.g. DefaultVecStorage) still return None for
+    // components that were never inserted, so the expect below only fires for absent components.
+    let removes = fields.iter().map(|field| {
+        let ComponentField {ident: field_name, is_optional, ..} = *field;
+        let ty = field.component_ty();
+        let remove = quote! {world.write_storage::<#ty>().remove(entity)};
+        let value = if is_optional {
+            // Optional components are often absent, so the presence of the component is checked
+            // through a read-only borrow first. The storage is only borrowed mutably if there is
+            // actually something to remove.
+            quote! {
+                if world.read_storage::<#ty>().contains(entity) {
+                    #remove
+                } else {
+                    None
+                }
+            }
+        } else if group_attrs.ignore_missing_on_remove {
+            quote! {#remove.unwrap_or_default()}
+        } else {
+            let err = format!("expected a {} component to be present", type_name(ty));
+            quote! {#remove.expect(#err)}
+        };
+        let value = if is_optional {
+            field.optional_component_into_field(value)
+        } else {
+            field.component_into_field(value)
+        };
+        quote! { let #field_name = #value; }
+    });
+    let tags = &group_attrs.tags;
+    let not_registered_panics = not_registered_panics(fields, &tags.iter().collect::<Vec<_>>(), group_attrs);
+    quote! {
+        fn remove(world: &mut #krate::World, entity: ::specs::Entity) -> Self {
+            use ::specs::WorldExt;
+            #not_registered_panics
+            #( #removes )*
+            #( world.write_storage::<#tags>().remove(entity); )*
 
             Self {
-                #( #field_names : #reads ),*
+                #( #field_names ),*
+                #skipped
             }
         }
     }
@@ -59,7 +59,10 @@
 //! * [Automatically Implementing `ComponentGroup`](#automatically-implementing-componentgroup)
 //! * [Optional Components](#optional-components)
 //! * [Fetching Multiple Component Group Instances](#fetching-multiple-component-group-instances)
+//! * [Creating Groups in Systems](#creating-groups-in-systems)
 //! * [Generic Component Groups](#generic-component-groups)
+//! * [Additional Generated Items](#additional-generated-items)
+//! * [Attributes](#attributes)
 //!
 //! # Motivation
 //!
@@ -206,7 +209,7 @@
 //! ```rust
 //! // Rust 2018 edition
 //! // Don't forget to add component_group as a dependency to your Cargo.toml file!
-//! use component_group::ComponentGroup;
+//! use component_group::{ComponentGroup, ReadableGroup};
 //! use specs::{World, WorldExt, Builder, Entity, Entities, Component, VecStorage, ReadStorage, WriteStorage, Join};
 //! use specs::error::Error as SpecsError;
 //! use specs_derive::Component;
@@ -231,10 +234,9 @@
 //!     health: Health,
 //! }
 //!
-//! impl ComponentGroup for PlayerComponents {
-//!     type UpdateError = SpecsError;
-//!
-//!     fn first_from_world(world: &World) -> Option<(Entity, Self)> {
+//! // Reading the group from the world is implemented separately from the rest of the trait
+//! impl ReadableGroup for PlayerComponents {
+//!     fn read_first_from_world(world: &World) -> Option<(Entity, Self)> {
 //!         // Needs to be updated every time the struct changes
 //!         let (entities, positions, velocities, healths) = world.system_data::<(
 //!             Entities,
@@ -251,7 +253,7 @@
 //!             }))
 //!     }
 //!
-//!     fn from_world(world: &World, entity: Entity) -> Self {
+//!     fn read_from_world(world: &World, entity: Entity) -> Self {
 //!         // Needs to be updated every time the struct changes
 //!         let (positions, velocities, healths) = world.system_data::<(
 //!             ReadStorage<Position>,
@@ -269,6 +271,10 @@
 //!                 .expect("expected a Health component to be present"),
 //!         }
 //!     }
+//! }
+//!
+//! impl ComponentGroup for PlayerComponents {
+//!     type UpdateError = SpecsError;
 //!
 //!     fn create(self, world: &mut World) -> Entity {
 //!         // It's possible to write this code so that the compiler will at the very least
@@ -318,6 +324,20 @@
 //!                 .expect("expected a Health component to be present"),
 //!         }
 //!     }
+//!
+//!     fn component_names_present(world: &World, entity: Entity) -> Vec<&'static str> {
+//!         // Needs to be updated every time the struct changes
+//!         let (positions, velocities, healths) = world.system_data::<(
+//!             ReadStorage<Position>,
+//!             ReadStorage<Velocity>,
+//!             ReadStorage<Health>,
+//!         )>();
+//!         let mut names = Vec::new();
+//!         if positions.contains(entity) { names.push("Position"); }
+//!         if velocities.contains(entity) { names.push("Velocity"); }
+//!         if healths.contains(entity) { names.push("Health"); }
+//!         names
+//!     }
 //! }
 //!
 //! # fn find_player_entity(world: &World) -> Entity {
@@ -531,9 +551,11 @@
 //! }
 //! ```
 //!
-//! **Note:** The way we match for the `Option` type is very naive right now. Using
-//! `Option<YourComponent>` as the type of your field will work, but using
-//! `std::option::Option<YourComponent>` will not.
+//! **Note:** Optional fields are detected by looking at how the type of the field is written.
+//! `Option<YourComponent>`, `std::option::Option<YourComponent>`, and
+//! `core::option::Option<YourComponent>` (with or without a leading `::`) are all detected, but
+//! other paths to `Option` (e.g. through a type alias) are not. Mark those fields with
+//! [`#[component_group(optional)]`](#component_groupoptional) instead.
 //!
 //! # Fetching Multiple Component Group Instances
 //!
@@ -598,11 +620,72 @@
 //! }
 //! ```
 //!
+//! # Creating Groups in Systems
+//!
+//! The methods of [`ComponentGroup`] need mutable access to the `World`, so they cannot be used
+//! from within a system. Instead, allocate the entity with `Entities::create` and use the
+//! generated `attach_lazy` method to queue the components with `LazyUpdate`. The entity can be
+//! used (e.g. stored in another component) immediately. Its components are added the next time
+//! the world is maintained.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{World, WorldExt, System, RunNow, Entities, Read, LazyUpdate, Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Health(u32);
+//! #[derive(ComponentGroup)]
+//! struct PlayerComponents {
+//!     position: Position,
+//!     health: Health,
+//! }
+//!
+//! struct SpawnPlayer;
+//!
+//! impl<'a> System<'a> for SpawnPlayer {
+//!     type SystemData = (Entities<'a>, Read<'a, LazyUpdate>);
+//!
+//!     fn run(&mut self, (entities, lazy): Self::SystemData) {
+//!         let entity = entities.create();
+//!         PlayerComponents {
+//!             position: Position {x: 12, y: 59},
+//!             health: Health(5),
+//!         }.attach_lazy(entity, &lazy);
+//!     }
+//! }
+//!
+//! fn main() {
+//!     let mut world = World::new();
+//!     world.register::<Position>();
+//!     world.register::<Health>();
+//!
+//!     SpawnPlayer.run_now(&world);
+//!     // Adds the components queued by the system
+//!     world.maintain();
+//!     let (_, player) = PlayerComponents::first_from_world(&world).unwrap();
+//!     assert_eq!(player.health.0, 5);
+//! }
+//! ```
+//!
+//! If the entity does not need to exist before the world is maintained, the generated
+//! `lazy_build` method can be used with `LazyUpdate::create_entity` instead.
+//!
 //! # Generic Component Groups
 //!
 //! It is possible to use the [`ComponentGroup`] trait and custom derive with generic structs. Just
 //! make sure to add `Send + Sync + Component + Clone` trait bounds to the generic type parameters
 //! or you will get a compile error. (The `Send + Sync` part is required by the `specs` crate.)
+//! The same bounds are needed for type parameters that are only used in optional fields
+//! (e.g. `Option<T>`). Type parameters may also have a default type
+//! (e.g. `struct PlayerComponents<V: ... = Velocity>`).
+//!
+//! Fields with a `PhantomData` type are not treated as components. The custom derive skips them
+//! and sets them to `PhantomData` whenever it creates an instance of the struct. This allows a
+//! type parameter to be used as a marker without it being a component.
 //!
 //! ```rust,no_run
 //! # use component_group::ComponentGroup;
@@ -640,27 +723,895 @@
 //! # }
 //! ```
 //!
+//! # Additional Generated Items
+//!
+//! Besides implementing [`ComponentGroup`], the custom derive generates some extra items that
+//! depend on the exact fields of the struct and therefore cannot be part of the trait. For a
+//! struct named `PlayerComponents`, the following items are generated:
+//!
+//! * `PlayerComponentsRead<'a>` - a struct that holds a `ReadStorage` for each component in the
+//!   group. It has a method for each field (e.g. `position(&self, entity)`) that returns a
+//!   reference to that entity's component, if present. This lets you read many entities from a
+//!   single fetch without cloning anything.
+//! * `PlayerComponents::read_world(&World) -> PlayerComponentsRead` - fetches the storages
+//!   into a new `PlayerComponentsRead`
+//! * `PlayerComponentsColumns` - a struct with a public `Vec` for each field (e.g.
+//!   `position: Vec<Position>` and `animation: Vec<Option<Animation>>`) and an `entities` field
+//!   with the entity of each row. This stores many groups as columns (struct-of-arrays) instead of
+//!   as a list of structs. It implements `Clone`, `Debug`, and `PartialEq` if every column does.
+//!   `PlayerComponentsColumns::new(Vec<Entity>, Vec<Position>, ...)` creates it from the entities
+//!   and the columns in the order that the fields are declared.
+//! * `PlayerComponents::columns_from_world(&World) -> PlayerComponentsColumns` - extracts every
+//!   instance of the group in the world into a new `PlayerComponentsColumns`
+//! * `PlayerComponents::create_from_columns(PlayerComponentsColumns, &mut World) -> Vec<Entity>` -
+//!   creates a new entity for each row of the columns, fetching each storage only once
+//! * `PlayerComponents::try_update(self, &mut World, Entity)` - the same as
+//!   [`update`](trait.ComponentGroup.html#tymethod.update), but the error is returned along with
+//!   the name of the field whose component could not be written (e.g. `("position", error)`)
+//! * `PlayerComponentsFields` - a struct with a public `bool` flag for each field that selects a
+//!   subset of the fields. `PlayerComponentsFields::all()` selects every field and the `Default`
+//!   value selects none of them.
+//! * `PlayerComponents::update_selected(self, &mut World, Entity, PlayerComponentsFields)` - the
+//!   same as [`update`](trait.ComponentGroup.html#tymethod.update), but only the selected fields
+//!   are written and the components of the other fields are left untouched
+//! * `PlayerComponentsReaders` - a struct with a public `ReaderId<ComponentEvent>` for each field
+//! * `PlayerComponents::create_with_readers(self, &mut World) -> (Entity, PlayerComponentsReaders)` -
+//!   the same as [`create`], but also registers a reader with the storage of each field so that
+//!   later changes can be tracked. Can only be called if every component uses a tracked storage
+//!   like `FlaggedStorage`.
+//! * `PlayerComponents::update_returning_removed(self, &mut World, Entity)` - the same as
+//!   [`update`](trait.ComponentGroup.html#tymethod.update), but returns the names of the optional
+//!   fields whose components were removed because they were `None` (e.g. `vec!["animation"]`)
+//! * `PlayerComponentsFieldValue` - an enum with a variant that holds the value of each field
+//!   (e.g. `PlayerComponentsFieldValue::Position(Position)`). Its `field_name` method returns the
+//!   name of the field that the value is for. The variants are named by converting each field name
+//!   to CamelCase, so two fields that convert to the same name (e.g. `foo_1` and `foo1`) are an
+//!   error.
+//! * `PlayerComponents::apply_fields(&mut World, Entity, &HashMap<String, PlayerComponentsFieldValue>, bool)` -
+//!   updates only the fields named in the map, which is useful for applying edits from tools like
+//!   a debug console. Unknown names are ignored if the last argument is true and are an error
+//!   otherwise.
+//! * `PlayerComponents::update_with_events(self, &mut World, Entity)` - the same as [`update`],
+//!   but returns the [`EventKind`] produced by writing each field. This is useful for debugging
+//!   the events emitted by flagged storages.
+//! * `PlayerComponents::update_stats(self, &mut World, Entity) -> Result<UpdateStats, _>` - the
+//!   same as [`update`], but returns the number of components that were inserted, modified, and
+//!   removed. Components that are overwritten with an equal value are not counted as modified, so
+//!   every component must implement `PartialEq`.
+//! * `PlayerComponents::remove_audited(&mut World, Entity) -> (PlayerComponents, Vec<&'static str>)` -
+//!   the same as [`remove`], but also returns the names of the fields whose components were
+//!   present and removed (e.g. `vec!["position", "health"]` if there was no animation)
+//! * `PlayerComponents::clear_optionals(&mut World, Entity)` - removes the components of every
+//!   optional field in the group from the entity and leaves the required components alone.
+//!   Returns an error if the entity is not alive.
+//! * `PlayerComponents::copy_health(&mut World, from: Entity, to: Entity)` - one method for each
+//!   field that copies only the component of that field from one entity to another. The
+//!   component of an optional field is removed from `to` if `from` does not have it.
+//! * `PlayerComponents::setup(&mut World)` - registers every component used by the group,
+//!   including the components of optional fields, so the group can be used with a fresh `World`
+//! * `PlayerComponents::take_all_from_world(&mut World) -> Vec<(Entity, PlayerComponents)>` -
+//!   removes the group from every entity that has all of the required components in the group
+//!   and returns the removed groups. If every field is optional, only the entities that have at
+//!   least one of the components are included.
+//! * `PlayerComponents::matches(&World, Entity) -> bool` - returns true if the entity has every
+//!   required component in the group, stopping at the first one that is missing
+//! * `PlayerComponents::from_world_first_missing(&World, Entity) -> Result<PlayerComponents, &'static str>` -
+//!   like [`from_world`], but returns the name of the first required field whose component is
+//!   missing (e.g. `Err("health")`) instead of panicking
+//! * `PlayerComponents::find_from_world(&World, pred) -> Option<(Entity, PlayerComponents)>` -
+//!   like [`first_from_world`](trait.ComponentGroup.html#method.first_from_world), but returns
+//!   the first entity and group for which `pred(entity, &group)` returns true
+//! * `PlayerComponents::from_world_filtered(&World, &BitSet) -> Vec<(Entity, PlayerComponents)>` -
+//!   returns every entity in the `BitSet` that has all of the required components in the group,
+//!   along with its group
+//! * `PlayerComponents::join_with(&World, J) -> Vec<(Entity, PlayerComponents, J::Type)>` - joins
+//!   the storages of the group with any other `Join` (e.g. `&ReadStorage<Velocity>`) and returns
+//!   every entity that has all of the required components in the group and a value in the
+//!   other join, along with its group and that value
+//! * `PlayerComponents::edit_first(&mut World, f) -> bool` - calls `f` with the first entity that
+//!   has all of the required components in the group and mutable references to its components
+//!   (e.g. `f(entity, &mut position, &mut health, Option<&mut animation>)`), so they can be
+//!   modified in place. Returns false if there is no such entity.
+//! * `PlayerComponents::required_field_names()` and `PlayerComponents::optional_field_names()` -
+//!   return the names of the fields with required and optional components respectively
+//! * `PlayerComponents::field_type_name(&str) -> Option<&'static str>` - returns the name of the
+//!   component type of a field (e.g. `Some("Position")` for `"position"`)
+//! * `PlayerComponents::field_is_optional(&str) -> Option<bool>` - returns whether the component
+//!   of a field is optional (e.g. `Some(true)` for `"animation"`)
+//! * `PlayerComponents::FIELD_OPTIONAL: [bool; N]` - whether the component of each field is
+//!   optional, indexed by the position of the field in the struct (e.g. `[false, false, true]`)
+//! * `PlayerComponents::lazy_build(self, LazyBuilder) -> Entity` - like [`create`], but adds the
+//!   components through a `LazyBuilder` (e.g. from `LazyUpdate::create_entity`) so it can be used
+//!   from within a system
+//! * `PlayerComponents::extend_builder(self, EntityBuilder) -> EntityBuilder` - adds the
+//!   components to an `EntityBuilder` (e.g. from `World::create_entity`) without building the
+//!   entity, so other components can be added to the same entity before it is built
+//! * `PlayerComponents::attach_lazy(self, Entity, &LazyUpdate)` - adds the components to an
+//!   entity that was already allocated (e.g. with `Entities::create`) once the world is
+//!   maintained. See [Creating Groups in Systems](#creating-groups-in-systems).
+//! * `PlayerComponents::assert_matches_world(&self, &World, Entity)` - panics if any field is not
+//!   equal to the corresponding component of the entity. This is useful in tests. It is only
+//!   generated when debug assertions are enabled and can only be called if every component
+//!   implements `PartialEq` and `Debug`.
+//! * `PlayerComponents::par_set_all(self, &mut World)` - sets the components of every entity
+//!   that has all of the required components in the group to clones of the components in this
+//!   group, using a parallel join. This is only generated when the `parallel` feature of this
+//!   crate is enabled (it is enabled by default).
+//! * `PlayerComponents::to_json(&self) -> serde_json::Value` and
+//!   `PlayerComponents::from_json(&serde_json::Value) -> Result<PlayerComponents, serde_json::Error>` -
+//!   convert the group to and from a JSON object with a key for each field. Optional fields that
+//!   are missing from the object are read as `None`. These are only generated when the
+//!   `serde_json` feature of this crate is enabled and can only be called if every field
+//!   implements `Serialize` and `Deserialize`.
+//! * `impl Serialize for PlayerComponents` and `impl Deserialize for PlayerComponents` - represent
+//!   the group as a struct with a key for each field, so it can be sent to another world with any
+//!   serde format. Optional fields that are missing are deserialized as `None`. These are only
+//!   generated when the `serde` feature of this crate is enabled and can only be used if every
+//!   field implements `Serialize` and `Deserialize`. Do not also derive these traits for the
+//!   struct when the feature is enabled.
+//!
+//! Generated items have the same visibility as the struct they are derived for. The names of
+//! generated types start with the name of the struct. If that would clash with another type, use
+//! the `alias` [attribute](#attributes) to choose a different prefix.
+//!
+//! # Attributes
+//!
+//! The behaviour of the custom derive can be adjusted using `#[component_group(...)]` attributes.
+//!
+//! ## `#[component_group(ignore_missing_on_remove)]`
+//!
+//! By default, [`remove`] panics if a required component is not present. When this attribute is
+//! placed on the struct, any missing required component is set to `Default::default()` instead.
+//! This is useful when tearing down entities that may only be partially constructed. Every
+//! required component in the group must implement `Default`.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! #[derive(Debug, Clone, Default, Component)]
+//! #[storage(VecStorage)]
+//! pub struct Position {x: i32, y: i32}
+//!
+//! #[derive(ComponentGroup)]
+//! #[component_group(ignore_missing_on_remove)]
+//! struct PlayerComponents {
+//!     position: Position,
+//! }
+//! ```
+//!
+//! ## `#[component_group(warn_on_remove)]`
+//!
+//! [`update`] removes the component of any optional field that is set to `None`. This makes it
+//! easy to lose data by forgetting to set an optional field. When this attribute is placed on
+//! the struct, a warning is logged with the [`log`] crate every time a component that was present
+//! is removed this way. Fields that were already absent do not produce a warning.
+//!
+//! ## `#[component_group(deny_empty)]`
+//!
+//! A group where every field is [optional](#optional-components) can be used to [`create`] an
+//! entity without any components if every field is `None`. That is usually a mistake, so when
+//! this attribute is placed on the struct, [`create`] panics instead of creating the entity.
+//! Groups with at least one required field are never empty, so the attribute has no effect on
+//! them.
+//!
+//! ## `#[component_group(transparent)]`
+//!
+//! Marks a group that wraps a single component. The struct must have exactly one field. In
+//! addition to everything else that is generated, the group implements `Deref` and `DerefMut` to
+//! that field, so it can be used almost as if it were the component itself.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(ComponentGroup)]
+//! #[component_group(transparent)]
+//! struct Wrapped {
+//!     inner: Position,
+//! }
+//!
+//! let mut wrapped = Wrapped {inner: Position {x: 1, y: 2}};
+//! wrapped.x += 1;
+//! assert_eq!(wrapped.x, 2);
+//! ```
+//!
+//! ## `#[component_group(after_create = "path::to::function")]`
+//!
+//! Calls the given function at the end of [`create`] with the newly created entity. The function
+//! must have the signature `fn(Entity, &mut World)`. This can be used to run any setup that needs
+//! the new entity, like registering it in an index.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{World, WorldExt, Entity, Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(Default)]
+//! pub struct Players(Vec<Entity>);
+//!
+//! fn register_player(entity: Entity, world: &mut World) {
+//!     world.write_resource::<Players>().0.push(entity);
+//! }
+//!
+//! #[derive(ComponentGroup)]
+//! #[component_group(after_create = "register_player")]
+//! struct PlayerComponents {
+//!     position: Position,
+//! }
+//! ```
+//!
+//! ## `#[component_group(with = "path::to::Type")]`
+//!
+//! Adds a marker component that is not a field of the struct to every entity created by
+//! [`create`]. The component is created with `Default::default()` and stripped again by
+//! [`remove`]. This attribute can be repeated to add more than one marker component.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage, NullStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(Debug, Default, Component)]
+//! #[storage(NullStorage)]
+//! pub struct Spawned;
+//!
+//! #[derive(ComponentGroup)]
+//! #[component_group(with = "Spawned")]
+//! struct PlayerComponents {
+//!     position: Position,
+//! }
+//! ```
+//!
+//! ## `#[component_group(capture_extras = "path::to::Type")]`
+//!
+//! Generates a `remove_with_extras` method that removes the group like [`remove`] and also takes
+//! the given component out of the entity if it is present. The captured components are returned
+//! in an [`ExtraComponents`] that can insert them into an entity again. This is useful when
+//! evacuating an entity entirely, e.g. to move it to another world. This attribute can be
+//! repeated to capture more than one type.
+//!
+//! specs does not provide a way to take a component out of the world without knowing its type,
+//! so every type that should be captured must be listed.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{World, WorldExt, Component, VecStorage, NullStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(Debug, Default, Component)]
+//! #[storage(NullStorage)]
+//! pub struct Selected;
+//!
+//! #[derive(ComponentGroup)]
+//! #[component_group(capture_extras = "Selected")]
+//! struct PlayerComponents {
+//!     position: Position,
+//! }
+//!
+//! # fn main() -> Result<(), specs::error::Error> {
+//! # let mut world = World::new();
+//! # world.register::<Position>();
+//! # world.register::<Selected>();
+//! # let entity = PlayerComponents {position: Position {x: 1, y: 2}}.create(&mut world);
+//! let (group, extras) = PlayerComponents::remove_with_extras(&mut world, entity);
+//!
+//! let new_entity = group.create(&mut world);
+//! extras.insert_into(&mut world, new_entity)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## `#[component_group(version = "path::to::Type")]`
+//!
+//! Increments a version component that is not a field of the struct every time the group is
+//! written to an existing entity, e.g. by [`update`], [`update_many`], [`set_all`], or any of the
+//! generated methods that write some or all of its fields. Creating the group does not count as a
+//! write. The version component must implement
+//! the [`Increment`] trait and `Default`. If an entity does not have a version component yet, one
+//! is inserted with its default value and then incremented. This is useful for detecting
+//! conflicting changes, e.g. when applying updates received over the network.
+//!
+//! ```rust
+//! # use component_group::{ComponentGroup, Increment};
+//! # use specs::{World, WorldExt, Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(Debug, Default, Component)]
+//! #[storage(VecStorage)]
+//! pub struct Version(u64);
+//!
+//! impl Increment for Version {
+//!     fn increment(&mut self) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! #[derive(ComponentGroup)]
+//! #[component_group(version = "Version")]
+//! struct PlayerComponents {
+//!     position: Position,
+//! }
+//! ```
+//!
+//! ## `#[component_group(update_error = "path::to::Type", map_err = "path::to::function")]`
+//!
+//! Changes the `UpdateError` type of the derived implementation. Every error from specs is
+//! converted into that type before it is returned from [`update`] or any of the other generated
+//! methods that write components. The conversion uses `From<specs::error::Error>` unless a
+//! function with the signature `fn(specs::error::Error) -> Type` is given with `map_err`, which
+//! avoids having to implement `From` just for this. `map_err` can only be used together with
+//! `update_error`.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(Debug)]
+//! pub enum GameError {
+//!     Storage(specs::error::Error),
+//! }
+//!
+//! #[derive(ComponentGroup)]
+//! #[component_group(update_error = "GameError", map_err = "GameError::Storage")]
+//! struct PlayerComponents {
+//!     position: Position,
+//! }
+//! ```
+//!
+//! ## `#[component_group(alias = "Name")]`
+//!
+//! Changes the prefix used to name the [generated types](#additional-generated-items). By default,
+//! the name of the struct is used. With `alias = "Player"`, the derive would generate a
+//! `PlayerRead` type instead of `PlayerComponentsRead`.
+//!
+//! ## `#[component_group(remap_entities = "path::to::function")]`
+//!
+//! Implements [`remap_entities`] by calling the given function. The function must have the
+//! signature `fn(&mut Self, &HashMap<Entity, Entity>)`. Use this when a component in the group
+//! refers to other entities so that [`move_to_world`] can update those references.
+//!
+//! ```rust
+//! # use std::collections::HashMap;
+//! # use component_group::ComponentGroup;
+//! # use specs::{Entity, Component, VecStorage};
+//! # use specs_derive::Component;
+//! #[derive(Debug, Clone, Component)]
+//! #[storage(VecStorage)]
+//! pub struct Parent(Entity);
+//!
+//! fn remap_parent(group: &mut Child, entity_map: &HashMap<Entity, Entity>) {
+//!     if let Some(&parent) = entity_map.get(&group.parent.0) {
+//!         group.parent.0 = parent;
+//!     }
+//! }
+//!
+//! #[derive(ComponentGroup)]
+//! #[component_group(remap_entities = "remap_parent")]
+//! struct Child {
+//!     parent: Parent,
+//! }
+//! ```
+//!
+//! ## `#[component_group(crate = "path::to::component_group")]`
+//!
+//! The generated code refers to this crate and to specs with the absolute paths
+//! `::component_group` and `::specs`, so it works no matter what those names refer to in the
+//! module the struct is defined in. If this crate is only available through a re-export (e.g.
+//! from a game engine that wraps its dependencies), use this attribute to provide the path to
+//! that re-export instead.
+//!
+//! ```rust
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! mod my_engine {
+//!     pub mod ecs {
+//!         pub use component_group::*;
+//!     }
+//! }
+//!
+//! use my_engine::ecs::ComponentGroup;
+//!
+//! #[derive(ComponentGroup)]
+//! #[component_group(crate = "my_engine::ecs")]
+//! struct PlayerComponents {
+//!     position: Position,
+//! }
+//! ```
+//!
+//! ## `#[component_group(clone_bound = false)]`
+//!
+//! Allows the group to be derived for components that do not implement `Clone`. Reading a group
+//! from the world requires cloning its components, so with this attribute the `find_from_world`,
+//! `from_world_filtered`, `par_set_all`, `columns_from_world`, and `apply_fields` methods and the
+//! `Columns` and `FieldValue` types are not generated. [`ReadableGroup`] is not implemented
+//! either, so [`first_from_world`], [`from_world`], and the other methods of the trait that read
+//! the group from the world cannot be called. Only use this for groups that are created,
+//! updated, and removed, but never read.
+//!
+//! ## `#[component_group(skip)]`
+//!
+//! This attribute is placed on a field. The field is not treated as a component and is ignored
+//! by every generated method, just like a `PhantomData` field. This makes it possible to keep
+//! plain data that is not a `Component` (e.g. a label used for debugging) in the group struct.
+//! Whenever the group is read from the world (e.g. by [`from_world`] or [`remove`]), the field is
+//! set to its default value, so its type must implement `Default`. Its value is never written to
+//! the world.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(ComponentGroup)]
+//! struct PlayerComponents {
+//!     position: Position,
+//!     #[component_group(skip)]
+//!     label: String,
+//! }
+//! ```
+//!
+//! ## `#[component_group(optional)]`
+//!
+//! This attribute is placed on a field. Fields are normally detected as
+//! [optional](#optional-components) by looking for a type written as `Option<T>` or
+//! `std::option::Option<T>`. This attribute makes that explicit and also accepts any other path
+//! that ends with `Option<T>`, like a re-export of `Option` from another crate. Marking a field
+//! that does not have an `Option` type is a compile error.
+//!
+//! ## `#[component_group(required)]`
+//!
+//! This attribute is placed on a field with an `Option<T>` type. The component `T` is treated
+//! as required instead of optional, even though the field is an `Option`. This is useful when
+//! the field is only an `Option` to make the struct easier to construct. The field must be `Some`
+//! whenever the group is written to the world (e.g. by [`create`] or [`update`]), otherwise those
+//! methods panic. Whenever the group is read from the world, the field is always `Some`.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(ComponentGroup)]
+//! struct PlayerComponents {
+//!     #[component_group(required)]
+//!     position: Option<Position>,
+//! }
+//! ```
+//!
+//! ## `#[component_group(component = "path::to::Type")]`
+//!
+//! This attribute is placed on a field. The given type is used as the component stored in the
+//! world instead of the type of the field. The value of the field is converted into the component
+//! with `Into` whenever it is written and the component is converted back into the type of the
+//! field with `Into` whenever it is read. This makes it possible to use a wrapper type for a field
+//! (e.g. a newtype with extra methods) while storing the real component. For optional fields,
+//! the given type replaces the type inside the `Option`.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! #[derive(Debug, Clone, Component)]
+//! #[storage(VecStorage)]
+//! pub struct Position {x: i32, y: i32}
+//!
+//! #[derive(Debug, Clone)]
+//! pub struct Point(i32, i32);
+//!
+//! impl From<Point> for Position {
+//!     fn from(Point(x, y): Point) -> Self { Position {x, y} }
+//! }
+//!
+//! impl From<Position> for Point {
+//!     fn from(Position {x, y}: Position) -> Self { Point(x, y) }
+//! }
+//!
+//! #[derive(ComponentGroup)]
+//! struct PlayerComponents {
+//!     #[component_group(component = "Position")]
+//!     position: Point,
+//! }
+//! ```
+//!
+//! ## `#[component_group(validate_each = "path::to::function")]`
+//!
+//! This attribute is placed on a field. The given function is called with a reference to the
+//! value of the field before the group is written to the world. It must have the signature
+//! `fn(&T) -> Result<(), E>` where `T` is the component type and `E` can be converted into a
+//! `Box<dyn Error + Send + Sync>` (e.g. `String`). Optional fields are only validated when they
+//! are `Some`.
+//!
+//! Every field is validated before any component is written. If validation fails, [`update`]
+//! returns the error as a `specs::error::Error::Custom` and nothing is written. Since [`create`]
+//! cannot fail, it panics instead.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! #[derive(Debug, Clone, Component)]
+//! #[storage(VecStorage)]
+//! pub struct Position {x: i32, y: i32}
+//!
+//! fn on_screen(pos: &Position) -> Result<(), String> {
+//!     if pos.x >= 0 && pos.y >= 0 {
+//!         Ok(())
+//!     } else {
+//!         Err(format!("{:?} is off screen", pos))
+//!     }
+//! }
+//!
+//! #[derive(ComponentGroup)]
+//! struct PlayerComponents {
+//!     #[component_group(validate_each = "on_screen")]
+//!     position: Position,
+//! }
+//! ```
+//!
+//! ## `#[component_group(read_with = "path::to::function")]`
+//!
+//! This attribute is placed on a field. The given function is called with every component read
+//! from the world for this field (e.g. in [`from_world`], [`first_from_world`], and [`remove`])
+//! and its result is used as the value of the field. The function must have the signature
+//! `fn(C) -> T` where `C` is the component type and `T` is the type of the field (without the
+//! `Option`). This can be used to post-process a component after it is loaded, like decompressing
+//! it. When combined with the `component` attribute, it is used instead of `Into`.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! #[derive(Debug, Clone, Component)]
+//! #[storage(VecStorage)]
+//! pub struct Name(String);
+//!
+//! fn trim_name(name: Name) -> Name {
+//!     Name(name.0.trim().to_string())
+//! }
+//!
+//! #[derive(ComponentGroup)]
+//! struct PlayerComponents {
+//!     #[component_group(read_with = "trim_name")]
+//!     name: Name,
+//! }
+//! ```
+//!
+//! ## `#[component_group(skip_if = "path::to::function")]`
+//!
+//! This attribute is placed on a field. The given function is called with a reference to the
+//! entire group before it is written to the world and must have the signature `fn(&Self) -> bool`.
+//! If it returns true, the component of this field is not written. [`create`] does not add the
+//! component and [`update`] leaves the existing component (or its absence) untouched. This applies
+//! to optional fields too, so a skipped optional field that is `None` does not remove anything.
+//!
+//! Groups created from columns with `create_from_columns` always write every component.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(Debug, Clone, Component)]
+//! #[storage(VecStorage)]
+//! pub struct Velocity {x: i32, y: i32}
+//!
+//! fn is_static(group: &Body) -> bool {
+//!     group.velocity.x == 0 && group.velocity.y == 0
+//! }
+//!
+//! #[derive(ComponentGroup)]
+//! struct Body {
+//!     position: Position,
+//!     #[component_group(skip_if = "is_static")]
+//!     velocity: Velocity,
+//! }
+//! ```
+//!
+//! ## `#[component_group(tag)]`
+//!
+//! This attribute is placed on a field. A component that uses `NullStorage` cannot store any
+//! data, so a field with such a component can only mark whether the entity has it. Since that is
+//! easy to do by accident, the custom derive produces a warning for fields with a `NullStorage`
+//! component unless they are marked with this attribute. The storage can only be detected for
+//! fields whose type is not a type parameter.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage, NullStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(Debug, Clone, Default, Component)]
+//! #[storage(NullStorage)]
+//! pub struct Frozen;
+//!
+//! #[derive(ComponentGroup)]
+//! struct StatueComponents {
+//!     position: Position,
+//!     #[component_group(tag)]
+//!     frozen: Option<Frozen>,
+//! }
+//! ```
+//!
+//! ## `#[component_group(order = N)]`
+//!
+//! This attribute is placed on a field. [`create`], [`update`], [`update_many`], and [`set_all`]
+//! normally insert the components of a group in the order that the fields are declared in the
+//! struct. This attribute moves the field to position `N` in that order instead, which controls
+//! the order of the events emitted by flagged storages. Fields without this attribute use their
+//! position in the struct (starting from 0) and fields with the same position keep the order that
+//! they are declared in. Every other method still uses the order of the fields in the struct.
+//!
+//! ```rust
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Velocity {x: i32, y: i32}
+//! #[derive(ComponentGroup)]
+//! struct Body {
+//!     // Inserted after the velocity
+//!     #[component_group(order = 2)]
+//!     position: Position,
+//!     velocity: Velocity,
+//! }
+//! ```
+//!
+//! ## `#[component_group(intern)]`
+//!
+//! This attribute is placed on a field. When many groups are created at once with
+//! [`create_many`], every value of this component that is equal to a value seen earlier in the
+//! batch is replaced with a clone of that earlier value. If the component holds its data in an
+//! `Arc`, this makes all of the equal components share a single allocation. The component must
+//! implement `Hash` and `Eq`.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use component_group::ComponentGroup;
+//! # use specs::{Component, VecStorage};
+//! # use specs_derive::Component;
+//! # #[derive(Debug, Clone, Component)]
+//! # #[storage(VecStorage)]
+//! # pub struct Position {x: i32, y: i32}
+//! #[derive(Debug, Clone, PartialEq, Eq, Hash, Component)]
+//! #[storage(VecStorage)]
+//! pub struct Mesh(Arc<Vec<u8>>);
+//!
+//! #[derive(ComponentGroup)]
+//! struct Prop {
+//!     position: Position,
+//!     #[component_group(intern)]
+//!     mesh: Mesh,
+//! }
+//! ```
+//!
 //! [`ComponentGroup`]: trait.ComponentGroup.html
+//! [`ReadableGroup`]: trait.ReadableGroup.html
+//! [`create_many`]: trait.ComponentGroup.html#method.create_many
+//! [`create`]: trait.ComponentGroup.html#tymethod.create
+//! [`first_from_world`]: trait.ComponentGroup.html#method.first_from_world
+//! [`from_world`]: trait.ComponentGroup.html#method.from_world
+//! [`remove`]: trait.ComponentGroup.html#tymethod.remove
+//! [`update`]: trait.ComponentGroup.html#tymethod.update
+//! [`update_many`]: trait.ComponentGroup.html#method.update_many
+//! [`set_all`]: trait.ComponentGroup.html#method.set_all
+//! [`log`]: https://docs.rs/log
+//! [`remap_entities`]: trait.ComponentGroup.html#method.remap_entities
+//! [`move_to_world`]: trait.ComponentGroup.html#method.move_to_world
 //! [`specs::Component`]: https://docs.rs/specs/*/specs/trait.Component.html
 //! [`specs::World`]: https://docs.rs/specs/*/specs/world/struct.World.html
+//! [`ExtraComponents`]: struct.ExtraComponents.html
+//! [`EventKind`]: enum.EventKind.html
+//! [`Increment`]: trait.Increment.html
 //! [Generic Associated Types (GATs)]: https://github.com/rust-lang/rust/issues/44265
 
 #![deny(unused_must_use)]
 
 #[doc(hidden)] pub use component_group_derive::*;
+// Used by the code generated for the warn_on_remove attribute
+#[doc(hidden)] pub use log;
+// Used by the code generated when the serde or serde_json features are enabled
+#[cfg(any(feature = "serde", feature = "serde_json"))]
+#[doc(hidden)] pub use serde;
+#[cfg(feature = "serde_json")]
+#[doc(hidden)] pub use serde_json;
+
+#[cfg(feature = "test-support")]
+pub mod test_support;
+
+/// Expands to the given items only if the `parallel` feature is enabled. Used by the custom derive
+/// since it cannot check the features of this crate itself.
+#[cfg(feature = "parallel")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __parallel {
+    ($($item:tt)*) => { $($item)* };
+}
+
+#[cfg(not(feature = "parallel"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __parallel {
+    ($($item:tt)*) => {};
+}
+
+/// Expands to the given items only if the `serde` feature is enabled
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __serde {
+    ($($item:tt)*) => { $($item)* };
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __serde {
+    ($($item:tt)*) => {};
+}
 
-use specs::{World, Entity};
+/// Expands to the given items only if the `serde_json` feature is enabled
+#[cfg(feature = "serde_json")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __serde_json {
+    ($($item:tt)*) => { $($item)* };
+}
+
+#[cfg(not(feature = "serde_json"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __serde_json {
+    ($($item:tt)*) => {};
+}
+
+/// Used by the custom derive to warn about fields with a component that uses `NullStorage` but are
+/// not marked with `#[component_group(tag)]`
+///
+/// The warning is produced with autoref specialization: `(&Check::<T>(..)).check()` resolves to
+/// the method from `IsNullStorage` only if the component is known to use `NullStorage`, and
+/// calling `warn` on the value it returns uses a deprecated method. Otherwise, an extra reference
+/// is taken and the method from `IsOtherStorage` is used. This only works for concrete types.
+#[doc(hidden)]
+pub mod __null_storage {
+    use std::marker::PhantomData;
+
+    use specs::{Component, NullStorage};
+
+    pub struct Check<T>(pub PhantomData<T>);
+
+    pub struct NullStorageField;
+
+    impl NullStorageField {
+        #[deprecated(note = "this component uses NullStorage, so it cannot store any data. Mark \
+            the field with #[component_group(tag)] if it is only meant to mark the entity.")]
+        pub fn warn(self) {}
+    }
+
+    pub struct OtherField;
+
+    impl OtherField {
+        pub fn warn(self) {}
+    }
+
+    pub trait IsNullStorage {
+        fn check(&self) -> NullStorageField {
+            NullStorageField
+        }
+    }
+
+    impl<T: Component<Storage = NullStorage<T>>> IsNullStorage for Check<T> {}
+
+    pub trait IsOtherStorage {
+        fn check(&self) -> OtherField {
+            OtherField
+        }
+    }
+
+    impl<T> IsOtherStorage for &Check<T> {}
+}
+
+/// Asserts that the components of an entity in the world are equal to the fields of the given
+/// group, panicking with the name of the first field that does not match.
+///
+/// This is a shorthand for the `assert_matches_world` method generated by the custom derive, so
+/// it has the same requirements: debug assertions must be enabled and every component must
+/// implement `PartialEq` and `Debug`.
+///
+/// ```rust
+/// # use component_group::{ComponentGroup, assert_group_eq};
+/// # use specs::{World, WorldExt, Component, VecStorage};
+/// # use specs_derive::Component;
+/// # #[derive(Debug, Clone, PartialEq, Component)]
+/// # #[storage(VecStorage)]
+/// # pub struct Position {x: i32, y: i32}
+/// # #[derive(Debug, Clone, PartialEq, Component)]
+/// # #[storage(VecStorage)]
+/// # pub struct Health(u32);
+/// #[derive(ComponentGroup, Clone)]
+/// struct PlayerComponents {
+///     position: Position,
+///     health: Health,
+/// }
+///
+/// # fn main() {
+/// let mut world = World::new();
+/// world.register::<Position>();
+/// world.register::<Health>();
+/// let entity = PlayerComponents {
+///     position: Position {x: 12, y: 59},
+///     health: Health(5),
+/// }.create(&mut world);
+///
+/// assert_group_eq!(world, entity, PlayerComponents {
+///     position: Position {x: 12, y: 59},
+///     health: Health(5),
+/// });
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_group_eq {
+    ($world:expr, $entity:expr, $group:expr $(,)?) => {
+        $group.assert_matches_world(&$world, $entity)
+    };
+}
+
+use std::fmt;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use specs::{WorldExt, Builder, Entity, Component, Join};
+
+/// The world type used by the trait and by the code generated by the custom derive
+///
+/// The generated code refers to the world through this alias instead of `specs::World`, so its
+/// signatures always match the trait even if the crate using the derive resolves `specs` to a
+/// different version than this crate does.
+#[doc(hidden)]
+pub type World = specs::World;
 
 /// Represents a group of [`specs::Component`] fields that can be added or extracted from
 /// a [`specs::World`].
 ///
 /// To automatically derive this trait using `#[derive(ComponentGroup)]`, all components within
-/// the group must implement the `Clone` trait.
+/// the group must implement the `Clone` trait. The methods that read the group from the world are
+/// only available if the group also implements [`ReadableGroup`].
 ///
 /// See the [top-level crate documentation](index.html) for more details.
 ///
 /// [`specs::Component`]: https://docs.rs/specs/*/specs/trait.Component.html
 /// [`specs::World`]: https://docs.rs/specs/*/specs/world/struct.World.html
+/// [`ReadableGroup`]: trait.ReadableGroup.html
 pub trait ComponentGroup: Sized {
     /// The error type from the [`update` method](#tymethod.update)
     type UpdateError;
@@ -672,35 +1623,643 @@ pub trait ComponentGroup: Sized {
     ///
     /// Returns `None` if any of the required fields could not be populated. Fields with an
     /// `Option` type will be set to `None` if their component could not be populated.
-    fn first_from_world(world: &World) -> Option<(Entity, Self)>;
+    fn first_from_world(world: &World) -> Option<(Entity, Self)>
+        where Self: ReadableGroup,
+    {
+        <Self as ReadableGroup>::read_first_from_world(world)
+    }
+
+    /// Same as [`first_from_world`](#method.first_from_world), but if more than one entity has
+    /// this group, the one with the lowest entity id is always returned.
+    ///
+    /// This is useful when tests or replays need to pick the same entity every time.
+    fn first_from_world_sorted(world: &World) -> Option<(Entity, Self)>
+        where Self: ReadableGroup,
+    {
+        <Self as ReadableGroup>::read_first_from_world_sorted(world)
+    }
 
     /// Extracts this group of components for the given entity from the given world.
     ///
     /// Panics if one of the component fields could not be populated. This can happen if the
     /// component does not exist for this entity. If the field is an `Option` type, its value will
     /// be set to `None` instead of panicking.
-    fn from_world(world: &World, entity: Entity) -> Self;
+    fn from_world(world: &World, entity: Entity) -> Self
+        where Self: ReadableGroup,
+    {
+        <Self as ReadableGroup>::read_from_world(world, entity)
+    }
+
+    /// Reads this group for the given entity with [`from_world`](#method.from_world), then calls
+    /// the given function with the group and the world. Returns the result of the function.
+    ///
+    /// Every storage borrowed to read the group is released before the function is called, so
+    /// the function is free to modify the world (e.g. to [`update`](#tymethod.update) another
+    /// entity based on the group).
+    ///
+    /// Panics if one of the required component fields could not be populated.
+    fn read_then<R, F>(world: &mut World, entity: Entity, f: F) -> R
+        where Self: ReadableGroup,
+              F: FnOnce(Self, &mut World) -> R,
+    {
+        let group = Self::from_world(world, entity);
+        f(group, world)
+    }
 
     /// Creates a new entity in the world and adds all the components from this group to that entity.
     ///
     /// Any fields with a value of `None` will not be added to the created entity.
+    ///
+    /// The custom derive adds the components in the order that the fields are declared in the
+    /// struct. The same order is used by all of the other methods. It also panics with a
+    /// [`ComponentNotRegistered`] message if any component that would be added was never
+    /// registered in the world.
+    ///
+    /// [`ComponentNotRegistered`]: struct.ComponentNotRegistered.html
     fn create(self, world: &mut World) -> Entity;
 
+    /// Creates a new entity with the given group if it is `Some`. Returns the created entity or
+    /// `None` if no entity was created.
+    fn create_opt(group: Option<Self>, world: &mut World) -> Option<Entity> {
+        group.map(|group| group.create(world))
+    }
+
+    /// Same as [`create`](#tymethod.create), but for a world that is shared behind a lock. The
+    /// write lock is held for as long as the group is being created.
+    ///
+    /// `World` can only be shared between threads if the `parallel` feature of this crate is
+    /// enabled (it is enabled by default).
+    ///
+    /// Panics if the lock was poisoned.
+    fn create_shared(self, world: &Arc<RwLock<World>>) -> Entity {
+        let mut world = world.write().expect("the lock around the world was poisoned");
+        self.create(&mut world)
+    }
+
+    /// Creates a new entity in the world with all the components from this group, then reads the
+    /// group back from the world. Returns the created entity along with the group that was read.
+    ///
+    /// This is the same as calling [`create`](#tymethod.create) followed by
+    /// [`from_world`](#method.from_world).
+    fn create_and_read(self, world: &mut World) -> (Entity, Self)
+        where Self: ReadableGroup,
+    {
+        let entity = self.create(world);
+        (entity, Self::from_world(world, entity))
+    }
+
+    /// Creates a new entity in the world with the same id (index) as the `source` entity from
+    /// another world, then adds all the components from this group to that entity.
+    ///
+    /// specs does not allow an entity to be created with a chosen id, so this is only best-effort.
+    /// Entities are allocated until one with the requested id is found, and all the other
+    /// allocated entities are deleted again. If the id is already taken by an entity that is alive
+    /// in `world`, the group is created with a different id. At most 1024 entities are allocated
+    /// while searching, so an id that is far past every id that `world` has used so far is not
+    /// preserved either. Use [`Entity::id`] to check which id was used. The generation of the
+    /// entity is never preserved.
+    ///
+    /// [`Entity::id`]: https://docs.rs/specs/*/specs/struct.Entity.html#method.id
+    fn create_preserving_id(self, source: Entity, world: &mut World) -> Entity {
+        const MAX_ALLOCATIONS: usize = 1024;
+
+        let taken = world.entities().join().any(|entity| entity.id() == source.id());
+        if taken {
+            return self.create(world);
+        }
+
+        let mut skipped = Vec::new();
+        let mut found = None;
+        for _ in 0..MAX_ALLOCATIONS {
+            let entity = world.create_entity().build();
+            if entity.id() == source.id() {
+                found = Some(entity);
+                break;
+            }
+            skipped.push(entity);
+            // Ids of deleted entities are reused (most recently deleted first) before any new id
+            // is used. An id that has never been used before has the first generation, and new
+            // ids only increase, so once one past the source has been reached, the source id can
+            // no longer be allocated.
+            if entity.gen().id() == 1 && entity.id() > source.id() {
+                break;
+            }
+        }
+
+        world.delete_entities(&skipped).expect("bug: skipped entities should still be alive");
+        // The most recently deleted entity is the next one to be allocated
+        if let Some(entity) = found {
+            world.delete_entity(entity).expect("bug: found entity should still be alive");
+        }
+        self.create(world)
+    }
+
+    /// Same as [`create`](#tymethod.create), but returns a [`CreatedEntity`] that keeps borrowing
+    /// the world so more work can be chained onto the new entity.
+    ///
+    /// ```rust,no_run
+    /// # use component_group::ComponentGroup;
+    /// # use specs::{World, WorldExt, Component, VecStorage, NullStorage};
+    /// # use specs_derive::Component;
+    /// # #[derive(Debug, Clone, Component)]
+    /// # #[storage(VecStorage)]
+    /// # pub struct Position {x: i32, y: i32}
+    /// # #[derive(Debug, Default, Component)]
+    /// # #[storage(NullStorage)]
+    /// # pub struct Selected;
+    /// # #[derive(ComponentGroup)]
+    /// # struct PlayerComponents {
+    /// #     position: Position,
+    /// # }
+    /// # let mut world = World::new();
+    /// # let group = PlayerComponents {position: Position {x: 1, y: 2}};
+    /// let entity = group.create_chained(&mut world)
+    ///     .with_extra(|entity, world| {
+    ///         world.write_storage::<Selected>().insert(entity, Selected)
+    ///             .expect("entity was just created");
+    ///     })
+    ///     .entity();
+    /// ```
+    ///
+    /// [`CreatedEntity`]: struct.CreatedEntity.html
+    fn create_chained(self, world: &mut World) -> CreatedEntity<'_> {
+        let entity = self.create(world);
+        CreatedEntity {world, entity}
+    }
+
+    /// Creates a new entity for each group in the given list and returns the created entities in
+    /// the same order.
+    ///
+    /// With the custom derive, fields marked with `#[component_group(intern)]` are deduplicated
+    /// so that equal values of that component are all replaced with clones of the first equal
+    /// value. See the [attribute documentation](index.html#attributes) for more details.
+    fn create_many(world: &mut World, groups: Vec<Self>) -> Vec<Entity> {
+        groups.into_iter().map(|group| group.create(world)).collect()
+    }
+
+    /// Adds all the components from this group to an entity that already exists but does not have
+    /// any of them yet (e.g. an entity reserved with `world.entities().create()`).
+    ///
+    /// The custom derive does everything that [`create`](#tymethod.create) does, including adding
+    /// tags, running validations, and calling the `after_create` function. Unregistered
+    /// components, invalid values, and errors from specs (e.g. because the entity is no longer
+    /// alive) are returned as errors instead of causing a panic. The default implementation calls
+    /// [`update`](#tymethod.update).
+    fn create_on(self, world: &mut World, entity: Entity) -> Result<(), Self::UpdateError> {
+        self.update(world, entity)
+    }
+
+    /// Creates a new entity for each group in the given list and returns the created entities in
+    /// the same order as the groups.
+    ///
+    /// Unlike [`create_many`](#method.create_many), every entity is reserved before any components
+    /// are added, so nothing that runs while the components are added can allocate an entity in
+    /// between. The ids are allocated in the order of the groups. If the world has no deleted
+    /// entities whose ids can be reused, the ids are sequential.
+    ///
+    /// The components are added to each reserved entity with [`create_on`](#method.create_on), so
+    /// the entities end up the same as if they were created with [`create`](#tymethod.create).
+    /// If any group cannot be added, the error is returned right away. The entities reserved for
+    /// that group and the groups after it are left alive without any components.
+    fn create_many_ordered(world: &mut World, groups: Vec<Self>) -> Result<Vec<Entity>, Self::UpdateError> {
+        let entities: Vec<_> = {
+            let reserved = world.entities();
+            groups.iter().map(|_| reserved.create()).collect()
+        };
+        for (&entity, group) in entities.iter().zip(groups) {
+            group.create_on(world, entity)?;
+        }
+        Ok(entities)
+    }
+
     /// Update the components of a given entity with all of the components from this group.
     ///
     /// Any fields with a value of `None` will be explicitly removed from the given entity.
     ///
+    /// Components that the entity does not have yet are inserted. This means that `update` can
+    /// also add the whole group to an entity that was built without it, e.g. one created with
+    /// `world.create_entity()` and some other components.
+    ///
     /// Note: Any additional components that the entity has other than the ones covered by
     /// the fields of this group will be left untouched.
+    ///
+    /// The custom derive returns a [`ComponentNotRegistered`] error without writing anything if
+    /// any component in the group was never registered in the world.
+    ///
+    /// [`ComponentNotRegistered`]: struct.ComponentNotRegistered.html
     fn update(self, world: &mut World, entity: Entity) -> Result<(), Self::UpdateError>;
 
+    /// Same as [`update`](#tymethod.update), but nothing is written if the entity is no longer
+    /// alive. Returns `Ok(false)` in that case instead of an error and `Ok(true)` if the entity
+    /// was updated.
+    fn update_if_alive(self, world: &mut World, entity: Entity) -> Result<bool, Self::UpdateError> {
+        if !world.is_alive(entity) {
+            return Ok(false);
+        }
+        self.update(world, entity)?;
+        Ok(true)
+    }
+
+    /// Same as [`update`](#tymethod.update), but for a world that is shared behind a lock. The
+    /// write lock is held for as long as the entity is being updated.
+    ///
+    /// `World` can only be shared between threads if the `parallel` feature of this crate is
+    /// enabled (it is enabled by default).
+    ///
+    /// Panics if the lock was poisoned.
+    fn update_shared(self, world: &Arc<RwLock<World>>, entity: Entity) -> Result<(), Self::UpdateError> {
+        let mut world = world.write().expect("the lock around the world was poisoned");
+        self.update(&mut world, entity)
+    }
+
+    /// Updates the given entity with the given group if it is `Some`. Does nothing if the group
+    /// is `None`.
+    fn update_opt(group: Option<Self>, world: &mut World, entity: Entity) -> Result<(), Self::UpdateError> {
+        match group {
+            Some(group) => group.update(world, entity),
+            None => Ok(()),
+        }
+    }
+
+    /// Updates the components of many entities at once. Each entity is updated with the
+    /// components from the group it is paired with, exactly as if [`update`](#tymethod.update)
+    /// had been called for each pair in order.
+    ///
+    /// The custom derive implements this by fetching each storage only once for the entire batch
+    /// and then writing all of the values for one storage before moving on to the next, which is
+    /// faster than calling `update` in a loop. If any entity in the batch is dead or any value
+    /// fails validation, the entities are updated one at a time instead so that the same updates
+    /// are applied before the error is returned.
+    fn update_many(world: &mut World, updates: Vec<(Entity, Self)>) -> Result<(), Self::UpdateError> {
+        for (entity, group) in updates {
+            group.update(world, entity)?;
+        }
+        Ok(())
+    }
+
+    /// Updates the given entity only if its components still match `expected` (usually the value
+    /// read before computing this update). Returns `false` without writing anything if the
+    /// components were changed in the meantime, otherwise applies the update and returns `true`.
+    fn update_if_unchanged(
+        self,
+        world: &mut World,
+        entity: Entity,
+        expected: &Self,
+    ) -> Result<bool, Self::UpdateError>
+        where Self: ReadableGroup + PartialEq,
+    {
+        if Self::from_world(world, entity) != *expected {
+            return Ok(false);
+        }
+        self.update(world, entity)?;
+        Ok(true)
+    }
+
+    /// Reconciles the world with a snapshot of groups keyed by a stable id (e.g. an id assigned by
+    /// a server).
+    ///
+    /// For each id in the snapshot, the entity it maps to in `id_map` is updated with the group
+    /// from the snapshot. If the id is not in `id_map` or its entity is no longer alive, a new
+    /// entity is created for the group and the mapping from the id to that entity is recorded
+    /// in `id_map`.
+    ///
+    /// Ids in `id_map` that are not in the snapshot are left untouched.
+    fn reconcile(
+        snapshot: HashMap<u32, Self>,
+        id_map: &mut HashMap<u32, Entity>,
+        world: &mut World,
+    ) -> Result<(), Self::UpdateError> {
+        for (id, group) in snapshot {
+            match id_map.get(&id) {
+                Some(&entity) if world.is_alive(entity) => group.update(world, entity)?,
+                _ => {
+                    let entity = group.create(world);
+                    id_map.insert(id, entity);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates every entity in the given list with a clone of the components from this group,
+    /// exactly as if [`update`](#tymethod.update) had been called for each entity in order.
+    ///
+    /// The custom derive implements this by fetching each storage only once for all of the
+    /// entities.
+    fn set_all(self, world: &mut World, entities: &[Entity]) -> Result<(), Self::UpdateError>
+        where Self: Clone,
+    {
+        for &entity in entities {
+            self.clone().update(world, entity)?;
+        }
+        Ok(())
+    }
+
     /// Removes all the components from this group from their storages in the given world for the
     /// given entity. Returns the values of the removed components.
     ///
     /// Note: Any additional components that the entity has other than the ones covered by
     /// the fields of this group will be left untouched.
     ///
+    /// The custom derive removes the components in the order that the fields are declared in the
+    /// struct, the same order that [`create`](#tymethod.create) inserts them in. Removing a group
+    /// and creating it again therefore produces the same sequence of storage events every time.
+    ///
     /// Panics if one of the required component fields was not present for removal. If the field is
-    /// an `Option` type, its value when returned will be set to `None` instead of panicking.
+    /// an `Option` type, its value when returned will be set to `None` instead of panicking. The
+    /// custom derive can be configured to use a default value instead of panicking with the
+    /// `#[component_group(ignore_missing_on_remove)]` attribute.
     fn remove(world: &mut World, entity: Entity) -> Self;
+
+    /// Returns the names of the component types in this group that the given entity has, in the
+    /// order that the fields are declared (e.g. `["Position", "Animation"]`)
+    ///
+    /// An empty `Vec` means that none of the components of the group are left on the entity,
+    /// e.g. after the group was removed.
+    fn component_names_present(world: &World, entity: Entity) -> Vec<&'static str>;
+
+    /// Removes this group from the given entity like [`remove`](#tymethod.remove), then deletes
+    /// the entity from the world if it has no other components left.
+    ///
+    /// There is no general way to find out which components an entity has, so the given
+    /// `is_empty` function decides whether the entity should be deleted. It is called after the
+    /// group has been removed.
+    fn remove_and_cleanup<F>(world: &mut World, entity: Entity, is_empty: F) -> Self
+        where F: FnOnce(&World, Entity) -> bool,
+    {
+        let group = Self::remove(world, entity);
+        if is_empty(world, entity) {
+            world.delete_entity(entity).expect("bug: entity should still be alive after remove");
+        }
+        group
+    }
+
+    /// Replaces any entity references held by the components of this group using the given map
+    /// from entities in one world to entities in another.
+    ///
+    /// Does nothing by default. The custom derive can be configured to call a function that does
+    /// the remapping with the `#[component_group(remap_entities = "path")]` attribute.
+    fn remap_entities(&mut self, _entity_map: &HashMap<Entity, Entity>) {}
+
+    /// Reads this group from the given entity in `source` and creates a new entity with a copy of
+    /// it in `dest`. Returns the newly created entity.
+    ///
+    /// The entity in `source` is left untouched. Components that are not in the group are not
+    /// copied and optional components that the entity does not have are not added to the new
+    /// entity.
+    ///
+    /// Panics if one of the required component fields was not present in `source`.
+    fn copy_to_world(source: &World, entity: Entity, dest: &mut World) -> Entity
+        where Self: ReadableGroup,
+    {
+        Self::from_world(source, entity).create(dest)
+    }
+
+    /// Removes this group from the given entity in `source` and creates a new entity with it in
+    /// `dest`. Returns the newly created entity.
+    ///
+    /// Entity references in the group are remapped with [`remap_entities`](#method.remap_entities)
+    /// before the new entity is created. The mapping from the old entity to the new one is then
+    /// added to `entity_map`, so moving referenced entities first makes sure that the references
+    /// to them can be remapped.
+    ///
+    /// Panics if one of the required component fields was not present in `source`.
+    fn move_to_world(
+        source: &mut World,
+        entity: Entity,
+        dest: &mut World,
+        entity_map: &mut HashMap<Entity, Entity>,
+    ) -> Entity {
+        let mut group = Self::remove(source, entity);
+        group.remap_entities(entity_map);
+        let new_entity = group.create(dest);
+        entity_map.insert(entity, new_entity);
+        new_entity
+    }
+
+    /// Extracts this group of components for the given entity from a history of worlds. The
+    /// `frame` is the index of the world to read from in `history`.
+    ///
+    /// This is useful if you keep snapshots of previous frames (e.g. for rollback).
+    ///
+    /// Returns `None` if `frame` is out of range or if the entity is not alive in the world at
+    /// that frame. Otherwise, this behaves exactly like [`from_world`](#method.from_world) and
+    /// will panic if one of the required component fields could not be populated.
+    fn at_frame(history: &[World], frame: usize, entity: Entity) -> Option<Self>
+        where Self: ReadableGroup,
+    {
+        let world = history.get(frame)?;
+        if world.is_alive(entity) {
+            Some(Self::from_world(world, entity))
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads a [`ComponentGroup`] from the world
+///
+/// Reading a group clones its components out of their storages, so the custom derive does not
+/// implement this trait for groups derived with `#[component_group(clone_bound = false)]`. The
+/// methods of `ComponentGroup` that read from the world (e.g.
+/// [`from_world`](trait.ComponentGroup.html#method.from_world)) require this trait, so calling
+/// them on those groups fails to compile instead of panicking.
+///
+/// Those methods are implemented by calling the methods of this trait, so there is usually no
+/// need to call them directly or to import this trait.
+///
+/// [`ComponentGroup`]: trait.ComponentGroup.html
+pub trait ReadableGroup: ComponentGroup {
+    /// Called by [`ComponentGroup::first_from_world`](trait.ComponentGroup.html#method.first_from_world)
+    fn read_first_from_world(world: &World) -> Option<(Entity, Self)>;
+
+    /// Called by [`ComponentGroup::first_from_world_sorted`](trait.ComponentGroup.html#method.first_from_world_sorted)
+    ///
+    /// The default implementation returns the result of `read_first_from_world`, so manual
+    /// implementations should override it if that method does not already return the entity with
+    /// the lowest id.
+    fn read_first_from_world_sorted(world: &World) -> Option<(Entity, Self)> {
+        Self::read_first_from_world(world)
+    }
+
+    /// Called by [`ComponentGroup::from_world`](trait.ComponentGroup.html#method.from_world)
+    fn read_from_world(world: &World, entity: Entity) -> Self;
+}
+
+/// Migrates an entity from one group type to another. The components of the `Old` group are
+/// removed from the entity, passed to `convert`, and the resulting `New` group is used to
+/// [`update`](trait.ComponentGroup.html#tymethod.update) the same entity.
+///
+/// This is useful when a group is refactored over time (e.g. `PlayerComponentsV1` to
+/// `PlayerComponentsV2`). Components that are in both groups are removed and then added back, so
+/// the entity ends up with exactly the components of the `New` group and any components that are
+/// not covered by either group.
+///
+/// Panics if the entity is missing one of the required components of the `Old` group, exactly
+/// like [`remove`](trait.ComponentGroup.html#tymethod.remove).
+pub fn migrate<Old, New, F>(world: &mut World, entity: Entity, convert: F) -> Result<(), New::UpdateError>
+    where Old: ComponentGroup,
+          New: ComponentGroup,
+          F: FnOnce(Old) -> New,
+{
+    let old = Old::remove(world, entity);
+    convert(old).update(world, entity)
+}
+
+/// An entity that was just created along with the world it was created in, returned by
+/// [`create_chained`](trait.ComponentGroup.html#method.create_chained)
+pub struct CreatedEntity<'a> {
+    world: &'a mut World,
+    entity: Entity,
+}
+
+impl<'a> CreatedEntity<'a> {
+    /// Returns the created entity, ending the chain
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns the world that the entity was created in
+    pub fn world(&mut self) -> &mut World {
+        self.world
+    }
+
+    /// Calls the given function with the created entity and the world, e.g. to add components
+    /// that are not part of the group
+    pub fn with_extra<F: FnOnce(Entity, &mut World)>(self, f: F) -> Self {
+        f(self.entity, self.world);
+        self
+    }
+}
+
+/// A component that counts how many times its entity has been updated
+///
+/// Used with the `version` attribute of the custom derive. See the
+/// [top-level crate documentation](index.html) for more details.
+pub trait Increment {
+    /// Advances this version by one
+    fn increment(&mut self);
+}
+
+/// The kind of event that writing a component produces in a `FlaggedStorage`
+///
+/// Returned for each field by the `update_with_events` method generated by the custom derive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// The component was not present and was inserted
+    Inserted,
+    /// The component was present and was overwritten
+    Modified,
+    /// The component was present and was removed because its optional field was `None`
+    Removed,
+}
+
+/// The number of components inserted, modified, and removed by a single update
+///
+/// Returned by the `update_stats` method generated by the custom derive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct UpdateStats {
+    /// The number of components that were not present and were inserted
+    pub inserted: usize,
+    /// The number of components that were present and overwritten with a different value
+    pub modified: usize,
+    /// The number of components that were removed because their optional field was `None`
+    pub removed: usize,
+}
+
+/// The error returned when a group is written to a world that one of its components was never
+/// registered in. Holds the name of the component type.
+///
+/// [`update`](trait.ComponentGroup.html#tymethod.update) and every other method generated by the
+/// custom derive that writes components and returns a `Result` return this wrapped in
+/// `specs::error::Error::Custom` instead of letting the missing storage panic. Methods that cannot
+/// return an error, like [`create`](trait.ComponentGroup.html#tymethod.create) and
+/// [`remove`](trait.ComponentGroup.html#tymethod.remove), panic with this error as their message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentNotRegistered(pub &'static str);
+
+impl fmt::Display for ComponentNotRegistered {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the {} component was not registered in the world", self.0)
+    }
+}
+
+impl std::error::Error for ComponentNotRegistered {}
+
+/// Components that are not part of a group, captured from an entity so they can be added back to
+/// an entity later
+///
+/// The custom derive generates a `remove_with_extras` method that fills one of these with every
+/// type listed in the `capture_extras` [attribute](index.html#attributes). specs does not provide
+/// a way to take a component out of the world without knowing its type, so only listed types can
+/// be captured.
+#[derive(Default)]
+pub struct ExtraComponents {
+    components: HashMap<TypeId, Box<dyn CapturedComponent>>,
+}
+
+impl fmt::Debug for ExtraComponents {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExtraComponents")
+            .field("len", &self.components.len())
+            .finish()
+    }
+}
+
+impl ExtraComponents {
+    /// Creates an empty set of components
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Removes the component of type `T` from the given entity and stores it. Does nothing if the
+    /// entity does not have that component.
+    pub fn capture<T: Component + Send + Sync>(&mut self, world: &mut World, entity: Entity) {
+        if let Some(component) = world.write_storage::<T>().remove(entity) {
+            self.components.insert(TypeId::of::<T>(), Box::new(component));
+        }
+    }
+
+    /// Returns the captured component of type `T`, if any
+    pub fn get<T: Component + Send + Sync>(&self) -> Option<&T> {
+        self.components.get(&TypeId::of::<T>())
+            .and_then(|component| component.as_any().downcast_ref())
+    }
+
+    /// Returns the number of captured components
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns true if no components were captured
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Inserts every captured component into the given entity. Any existing components of the
+    /// same types are overwritten.
+    pub fn insert_into(self, world: &mut World, entity: Entity) -> Result<(), specs::error::Error> {
+        for (_, component) in self.components {
+            component.insert_into(world, entity)?;
+        }
+        Ok(())
+    }
+}
+
+/// A component of any type that can be inserted back into the world
+trait CapturedComponent: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn insert_into(self: Box<Self>, world: &mut World, entity: Entity) -> Result<(), specs::error::Error>;
+}
+
+impl<T: Component + Send + Sync> CapturedComponent for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn insert_into(self: Box<Self>, world: &mut World, entity: Entity) -> Result<(), specs::error::Error> {
+        world.write_storage::<T>().insert(entity, *self)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,40 @@
+//! Helpers for testing component groups, enabled by the `test-support` feature
+//!
+//! These make it easy for crates that define their own groups to check that those groups behave
+//! as expected when they are moved between worlds.
+
+use std::fmt::Debug;
+use std::collections::HashMap;
+
+use specs::{World, Entity};
+
+use crate::ReadableGroup;
+
+/// Moves the group of the given entity from one world to another with
+/// [`move_to_world`](../trait.ComponentGroup.html#method.move_to_world) and returns the entity that
+/// was created in the destination world
+///
+/// Panics if the group read from the new entity is not equal to the group that was read from the
+/// original entity before the move, or if the original entity still has any of the components in
+/// the group afterwards.
+pub fn assert_moved<G>(from: &mut World, to: &mut World, entity: Entity) -> Entity
+    where G: ReadableGroup + PartialEq + Clone + Debug,
+{
+    let expected = G::from_world(from, entity);
+
+    let mut entity_map = HashMap::new();
+    let new_entity = G::move_to_world(from, entity, to, &mut entity_map);
+
+    let moved = G::from_world(to, new_entity);
+    // Entity references are remapped during the move, so the expected group needs the same
+    // update before the two can be compared
+    let mut remapped = expected.clone();
+    remapped.remap_entities(&entity_map);
+    assert_eq!(moved, remapped, "the group was not fully moved to entity {:?}", new_entity);
+
+    let remaining = G::component_names_present(from, entity);
+    assert!(remaining.is_empty(), "the {:?} components of the group are still in the source world \
+        for entity {:?}", remaining, entity);
+
+    new_entity
+}
@@ -0,0 +1,27 @@
+// Tests that the methods that read a group cannot be called when the Clone bound is disabled
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(ComponentGroup)]
+#[component_group(clone_bound = false)]
+struct PlayerComponents {
+    position: Position,
+}
+
+fn main() {
+    let world = World::new();
+    PlayerComponents::find_from_world(&world, |_, _| true); //~ ERROR no function or associated item named `find_from_world` found
+    PlayerComponents::columns_from_world(&world); //~ ERROR no function or associated item named `columns_from_world` found
+    PlayerComponents::first_from_world(&world); //~ ERROR the trait bound `PlayerComponents: ReadableGroup` is not satisfied
+    PlayerComponents::from_world(&world, world.entities().create()); //~ ERROR the trait bound `PlayerComponents: ReadableGroup` is not satisfied
+}
@@ -0,0 +1,37 @@
+// Tests that fields whose names convert to the same variant of the FieldValue enum are rejected
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, Component)]
+#[storage(VecStorage)]
+pub struct Health(u32);
+
+#[derive(ComponentGroup, Clone)]
+struct Distinct { // This should not have any errors
+    max_speed: Position,
+    speed: Health,
+}
+
+#[derive(ComponentGroup, Clone)]
+struct Numbered {
+    foo_1: Position,
+    foo1: Health, //~ ERROR the `foo_1` and `foo1` fields would both be represented by the `Foo1` variant
+}
+
+#[derive(ComponentGroup, Clone)]
+struct Underscored {
+    a_b: Position,
+    a__b: Health, //~ ERROR the `a_b` and `a__b` fields would both be represented by the `AB` variant
+}
+
+fn main() {}
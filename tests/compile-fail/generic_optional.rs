@@ -0,0 +1,31 @@
+// Tests that a generic type parameter used only inside an Option is properly bounded
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{Component, VecStorage, NullStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+// This struct should not have any errors
+#[derive(ComponentGroup)]
+struct Group<A: Send + Sync + Component + Clone> {
+    position: Position,
+    aux: Option<A>,
+}
+
+// impls Component, but not Clone
+#[derive(Debug, Default, Component)]
+#[storage(NullStorage)]
+struct NotClone;
+
+fn foo1(g: Group<Position>) {} // No error
+fn foo2(g: Group<NotClone>) {}
+//~^ ERROR the trait bound `NotClone: Clone` is not satisfied [E0277]
+
+fn main() {}
@@ -0,0 +1,33 @@
+// Tests that a field with a NullStorage component produces a warning unless it is marked as a tag
+
+#![deny(deprecated)]
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{Component, VecStorage, NullStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, Default, Component)]
+#[storage(NullStorage)]
+pub struct Frozen;
+
+#[derive(Debug, Clone, Default, Component)]
+#[storage(NullStorage)]
+pub struct Hidden;
+
+#[derive(ComponentGroup)]
+pub struct StatueComponents {
+    position: Position,
+    frozen: Option<Frozen>, //~ ERROR use of deprecated method
+    #[component_group(tag)]
+    hidden: Option<Hidden>,
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+// Tests that the optional attribute can only be used on fields with an Option type
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(ComponentGroup)]
+struct PlayerComponents {
+    #[component_group(optional)]
+    position: Position, //~ ERROR fields marked with #[component_group(optional)] must have an Option type
+}
+
+fn main() {}
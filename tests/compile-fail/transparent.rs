@@ -0,0 +1,41 @@
+// Tests that the transparent attribute requires exactly one field
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use std::marker::PhantomData;
+
+use component_group::ComponentGroup;
+use specs::{Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, Component)]
+#[storage(VecStorage)]
+pub struct Health(u32);
+
+#[derive(ComponentGroup)]
+#[component_group(transparent)]
+struct Wrapped { // This should not have any errors
+    position: Position,
+}
+
+#[derive(ComponentGroup)]
+#[component_group(transparent)]
+struct TwoFields { //~ ERROR struct must have exactly one field to use #[component_group(transparent)]
+    position: Position,
+    health: Health,
+}
+
+#[derive(ComponentGroup)]
+#[component_group(transparent)]
+struct WithMarker { //~ ERROR struct must have exactly one field to use #[component_group(transparent)]
+    position: Position,
+    marker: PhantomData<u32>,
+}
+
+fn main() {}
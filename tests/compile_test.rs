@@ -3,10 +3,18 @@ extern crate compiletest_rs as compiletest;
 use std::path::PathBuf;
 
 fn run_mode(mode: &'static str) {
-    let mut config = compiletest::Config::default();
+    let mut config = compiletest::Config {
+        mode: mode.parse().expect("Invalid mode"),
+        src_base: PathBuf::from(format!("tests/{}", mode)),
+        ..Default::default()
+    };
+
+    // The tests are compiled separately from this crate, so they are told which of its features
+    // are enabled
+    if cfg!(feature = "serde") {
+        config.target_rustcflags = Some("--cfg feature=\"serde\"".to_string());
+    }
 
-    config.mode = mode.parse().expect("Invalid mode");
-    config.src_base = PathBuf::from(format!("tests/{}", mode));
     config.link_deps(); // Populate config.target_rustcflags with dependencies on the path
     config.clean_rmeta(); // If your tests import the parent crate, this helps with E0464
 
@@ -0,0 +1,837 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use component_group::{ComponentGroup, Increment};
+
+use specs::{World, WorldExt, Builder, Entity, Component, VecStorage, HashMapStorage, NullStorage, ReadStorage, WriteStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, Default, Component, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, Copy, Default, Component, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Health(u32);
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+    world.register::<Animation>();
+    world
+}
+
+fn get<C: Component + Clone>(world: &World, entity: Entity) -> Option<C> {
+    let storage = world.system_data::<ReadStorage<C>>();
+    storage.get(entity).cloned()
+}
+
+fn remove<C: Component + Clone>(world: &mut World, entity: Entity) {
+    let mut storage = world.system_data::<WriteStorage<C>>();
+    storage.remove(entity).unwrap();
+}
+
+#[test]
+fn remove_partially_present_group() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    #[component_group(ignore_missing_on_remove)]
+    struct PlayerComponents {
+        position: Position,
+        health: Health,
+        animation: Option<Animation>,
+    }
+
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    };
+    let entity = player.create(&mut world);
+
+    // Entity is only partially constructed now
+    remove::<Health>(&mut world, entity);
+
+    // Missing required components are replaced with their default value instead of panicking
+    let removed_player = PlayerComponents::remove(&mut world, entity);
+    assert_eq!(removed_player.position, Position {x: 12, y: 59});
+    assert_eq!(removed_player.health, Health::default());
+    assert_eq!(removed_player.animation, Some(Animation {frame: 2}));
+
+    // Everything that was present has still been removed
+    assert_eq!(get(&world, entity), None::<Position>);
+    assert_eq!(get(&world, entity), None::<Animation>);
+}
+
+#[derive(Debug, Default)]
+pub struct PlayerIndex(Vec<Entity>);
+
+fn index_player(entity: Entity, world: &mut World) {
+    world.write_resource::<PlayerIndex>().0.push(entity);
+}
+
+#[test]
+fn after_create_callback() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    #[component_group(after_create = "index_player")]
+    struct PlayerComponents {
+        position: Position,
+        health: Health,
+    }
+
+    let mut world = new_world();
+    world.insert(PlayerIndex::default());
+
+    let entity1 = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+    }.create(&mut world);
+    let entity2 = PlayerComponents {
+        position: Position {x: -3, y: 2},
+        health: Health(7),
+    }.create(&mut world);
+
+    // The callback ran once for each created entity, after its components were added
+    assert_eq!(world.read_resource::<PlayerIndex>().0, vec![entity1, entity2]);
+    assert_eq!(get(&world, entity2), Some(Health(7)));
+}
+
+#[test]
+fn create_many_ordered_runs_create_steps() {
+    #[derive(Debug, Clone, Copy, Default, Component, PartialEq, Eq)]
+    #[storage(NullStorage)]
+    pub struct Player;
+
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    #[component_group(after_create = "index_player", with = "Player")]
+    struct PlayerComponents {
+        #[component_group(validate_each = "in_bounds")]
+        position: Position,
+        health: Health,
+    }
+
+    let mut world = new_world();
+    world.register::<Player>();
+    world.insert(PlayerIndex::default());
+
+    let entities = PlayerComponents::create_many_ordered(&mut world, vec![
+        PlayerComponents {position: Position {x: 1, y: 2}, health: Health(5)},
+        PlayerComponents {position: Position {x: 3, y: 4}, health: Health(7)},
+    ]).unwrap();
+    // Every entity is the same as one created with `create`
+    assert_eq!(world.read_resource::<PlayerIndex>().0, entities);
+    for &entity in &entities {
+        assert_eq!(get(&world, entity), Some(Player));
+    }
+
+    let result = PlayerComponents::create_many_ordered(&mut world, vec![
+        PlayerComponents {position: Position {x: 1000, y: 2}, health: Health(5)},
+    ]);
+    match result {
+        Err(specs::error::Error::Custom(err)) => assert!(err.to_string().contains("out of bounds")),
+        result => panic!("expected the group to fail validation, got: {:?}", result),
+    }
+    assert_eq!(world.read_resource::<PlayerIndex>().0, entities);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MeshData {vertices: Vec<(i32, i32)>}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Component)]
+#[storage(VecStorage)]
+pub struct Mesh(Arc<MeshData>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Component)]
+#[storage(VecStorage)]
+pub struct Outline(Arc<MeshData>);
+
+#[test]
+fn create_many_interned() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Prop {
+        position: Position,
+        #[component_group(intern)]
+        mesh: Mesh,
+        #[component_group(intern)]
+        outline: Option<Outline>,
+    }
+
+    let mut world = new_world();
+    world.register::<Mesh>();
+    world.register::<Outline>();
+
+    let mesh_data = || Arc::new(MeshData {vertices: vec![(0, 0), (1, 0), (0, 1)]});
+    let props = (0..5).map(|i| Prop {
+        position: Position {x: i, y: i},
+        // Every mesh is a separate allocation before the groups are created
+        mesh: Mesh(mesh_data()),
+        outline: if i % 2 == 0 { Some(Outline(mesh_data())) } else { None },
+    }).collect();
+    let entities = Prop::create_many(&mut world, props);
+    assert_eq!(entities.len(), 5);
+
+    let first = Prop::from_world(&world, entities[0]);
+    for (i, &entity) in entities.iter().enumerate() {
+        let prop = Prop::from_world(&world, entity);
+        assert_eq!(prop.position, Position {x: i as i32, y: i as i32});
+        // All the equal meshes now share the same allocation
+        assert!(Arc::ptr_eq(&prop.mesh.0, &first.mesh.0));
+        match prop.outline {
+            Some(outline) => assert!(Arc::ptr_eq(&outline.0, &first.outline.as_ref().unwrap().0)),
+            None => assert!(i % 2 == 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Parent(Entity);
+
+#[test]
+fn move_with_remapped_entities() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Node {
+        position: Position,
+    }
+
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    #[component_group(remap_entities = "remap_parent")]
+    struct Child {
+        position: Position,
+        parent: Parent,
+    }
+
+    fn remap_parent(child: &mut Child, entity_map: &HashMap<Entity, Entity>) {
+        child.parent.0 = entity_map[&child.parent.0];
+    }
+
+    let mut source = new_world();
+    source.register::<Parent>();
+    let parent = Node {position: Position {x: 1, y: 2}}.create(&mut source);
+    let child = Child {position: Position {x: 3, y: 4}, parent: Parent(parent)}.create(&mut source);
+
+    let mut dest = new_world();
+    dest.register::<Parent>();
+    // Offset the entities in the destination world so the ids will not match
+    for _ in 0..3 {
+        dest.create_entity().build();
+    }
+
+    let mut entity_map = HashMap::new();
+    let new_parent = Node::move_to_world(&mut source, parent, &mut dest, &mut entity_map);
+    let new_child = Child::move_to_world(&mut source, child, &mut dest, &mut entity_map);
+    assert_ne!(new_parent, parent);
+    assert_eq!(entity_map[&parent], new_parent);
+    assert_eq!(entity_map[&child], new_child);
+
+    // The reference to the parent should now refer to the parent in the new world
+    assert_eq!(Child::from_world(&dest, new_child), Child {
+        position: Position {x: 3, y: 4},
+        parent: Parent(new_parent),
+    });
+    assert_eq!(get(&source, child), None::<Parent>);
+}
+
+/// Collects the messages of every warning logged by any test in this file
+struct WarningLogger(Mutex<Vec<String>>);
+
+impl log::Log for WarningLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: WarningLogger = WarningLogger(Mutex::new(Vec::new()));
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct Shield(u32);
+
+#[test]
+fn warn_on_remove_logs_removals() -> Result<(), specs::error::Error> {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    #[component_group(warn_on_remove)]
+    struct Guard {
+        position: Position,
+        shield: Option<Shield>,
+    }
+
+    log::set_logger(&LOGGER).expect("only this test should set the logger");
+    log::set_max_level(log::LevelFilter::Warn);
+    let shield_warnings = || LOGGER.0.lock().unwrap().iter().filter(|msg| msg.contains("Shield")).count();
+
+    let mut world = new_world();
+    world.register::<Shield>();
+    let entity = Guard {
+        position: Position {x: 1, y: 2},
+        shield: Some(Shield(3)),
+    }.create(&mut world);
+
+    Guard {position: Position {x: 2, y: 3}, shield: Some(Shield(4))}.update(&mut world, entity)?;
+    assert_eq!(shield_warnings(), 0);
+
+    // Only removing a component that was present produces a warning
+    Guard {position: Position {x: 3, y: 4}, shield: None}.update(&mut world, entity)?;
+    assert_eq!(shield_warnings(), 1);
+    Guard {position: Position {x: 4, y: 5}, shield: None}.update(&mut world, entity)?;
+    assert_eq!(shield_warnings(), 1);
+    assert_eq!(get(&world, entity), None::<Shield>);
+
+    Ok(())
+}
+
+fn in_bounds(pos: &Position) -> Result<(), String> {
+    if pos.x.abs() <= 100 && pos.y.abs() <= 100 {
+        Ok(())
+    } else {
+        Err(format!("{:?} is out of bounds", pos))
+    }
+}
+
+fn valid_frame(animation: &Animation) -> Result<(), String> {
+    if animation.frame < 60 {
+        Ok(())
+    } else {
+        Err(format!("frame {} does not exist", animation.frame))
+    }
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct BoundedComponents {
+    health: Health,
+    #[component_group(validate_each = "in_bounds")]
+    position: Position,
+    #[component_group(validate_each = "valid_frame")]
+    animation: Option<Animation>,
+}
+
+#[test]
+fn validate_each_rejects_update() {
+    let mut world = new_world();
+    let bounded = BoundedComponents {
+        health: Health(5),
+        position: Position {x: 1, y: 2},
+        animation: None,
+    };
+    let entity = bounded.clone().create(&mut world);
+
+    let res = BoundedComponents {
+        health: Health(10),
+        position: Position {x: 1000, y: 2},
+        animation: Some(Animation {frame: 1}),
+    }.update(&mut world, entity);
+    match res {
+        Err(specs::error::Error::Custom(err)) => assert!(err.to_string().contains("out of bounds")),
+        res => panic!("expected the update to fail validation, got: {:?}", res),
+    }
+    // Nothing should be written, not even the fields before the invalid one
+    assert_eq!(BoundedComponents::from_world(&world, entity), bounded);
+
+    let res = BoundedComponents {
+        health: Health(10),
+        position: Position {x: 1, y: 2},
+        animation: Some(Animation {frame: 100}),
+    }.update(&mut world, entity);
+    match res {
+        Err(specs::error::Error::Custom(err)) => assert!(err.to_string().contains("does not exist")),
+        res => panic!("expected the update to fail validation, got: {:?}", res),
+    }
+    assert_eq!(BoundedComponents::from_world(&world, entity), bounded);
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn validate_each_rejects_par_set_all() {
+    let mut world = new_world();
+    let bounded = BoundedComponents {
+        health: Health(5),
+        position: Position {x: 1, y: 2},
+        animation: None,
+    };
+    let entity = bounded.clone().create(&mut world);
+
+    // The invalid value is in an optional field, but the required fields must not be written
+    // either
+    let res = BoundedComponents {
+        health: Health(10),
+        position: Position {x: 3, y: 4},
+        animation: Some(Animation {frame: 100}),
+    }.par_set_all(&mut world);
+    match res {
+        Err(specs::error::Error::Custom(err)) => assert!(err.to_string().contains("does not exist")),
+        res => panic!("expected the update to fail validation, got: {:?}", res),
+    }
+    assert_eq!(BoundedComponents::from_world(&world, entity), bounded);
+}
+
+#[test]
+#[should_panic(expected = "invalid value for the `position` field")]
+fn validate_each_rejects_create() {
+    let mut world = new_world();
+    BoundedComponents {
+        health: Health(5),
+        position: Position {x: 1, y: -200},
+        animation: None,
+    }.create(&mut world);
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+#[component_group(deny_empty)]
+struct Extras {
+    position: Option<Position>,
+    animation: Option<Animation>,
+}
+
+#[test]
+fn deny_empty_allows_some_components() {
+    let mut world = new_world();
+    let entity = Extras {
+        position: None,
+        animation: Some(Animation {frame: 1}),
+    }.create(&mut world);
+    assert_eq!(get(&world, entity), None::<Position>);
+    assert_eq!(get(&world, entity), Some(Animation {frame: 1}));
+}
+
+#[test]
+#[should_panic(expected = "attempted to create an entity without any components")]
+fn deny_empty_rejects_create() {
+    let mut world = new_world();
+    Extras {
+        position: None,
+        animation: None,
+    }.create(&mut world);
+}
+
+#[derive(Debug, Clone, Copy, Default, Component, PartialEq, Eq)]
+#[storage(NullStorage)]
+pub struct Spawned;
+
+#[derive(Debug, Clone, Copy, Default, Component, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct Team(u32);
+
+#[test]
+fn remove_with_captured_extras() -> Result<(), specs::error::Error> {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    #[component_group(capture_extras = "Spawned", capture_extras = "Team")]
+    struct Unit {
+        position: Position,
+    }
+
+    let mut world = new_world();
+    world.register::<Spawned>();
+    world.register::<Team>();
+    let entity = Unit {position: Position {x: 1, y: 2}}.create(&mut world);
+    world.write_storage::<Spawned>().insert(entity, Spawned)?;
+
+    let (unit, extras) = Unit::remove_with_extras(&mut world, entity);
+    assert_eq!(unit, Unit {position: Position {x: 1, y: 2}});
+    // Only the components that were present are captured
+    assert_eq!(extras.len(), 1);
+    assert_eq!(extras.get::<Spawned>(), Some(&Spawned));
+    assert_eq!(extras.get::<Team>(), None);
+    assert_eq!(get(&world, entity), None::<Spawned>);
+
+    let new_entity = unit.create(&mut world);
+    extras.insert_into(&mut world, new_entity)?;
+    assert_eq!(get(&world, new_entity), Some(Spawned));
+    assert_eq!(get(&world, new_entity), None::<Team>);
+
+    Ok(())
+}
+
+#[test]
+fn create_and_remove_with_tags() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    #[component_group(with = "Spawned", with = "Team")]
+    struct Minion {
+        position: Position,
+    }
+
+    let mut world = new_world();
+    world.register::<Spawned>();
+    world.register::<Team>();
+    let entity = Minion {position: Position {x: 1, y: 2}}.create(&mut world);
+    assert_eq!(get(&world, entity), Some(Spawned));
+    assert_eq!(get(&world, entity), Some(Team(0)));
+
+    let minion = Minion::remove(&mut world, entity);
+    assert_eq!(minion.position, Position {x: 1, y: 2});
+    assert_eq!(get(&world, entity), None::<Spawned>);
+    assert_eq!(get(&world, entity), None::<Team>);
+}
+
+#[derive(Debug, Clone, Copy, Default, Component, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Velocity {x: i32, y: i32}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct Body {
+    position: Position,
+    #[component_group(skip_if = "is_static")]
+    velocity: Velocity,
+}
+
+fn is_static(body: &Body) -> bool {
+    body.velocity == Velocity::default()
+}
+
+#[test]
+fn skip_if_skips_writing_field() -> Result<(), specs::error::Error> {
+    let mut world = new_world();
+    world.register::<Velocity>();
+
+    let resting = Body {position: Position {x: 1, y: 2}, velocity: Velocity::default()};
+    let entity = resting.create(&mut world);
+    assert_eq!(get(&world, entity), Some(Position {x: 1, y: 2}));
+    assert_eq!(get(&world, entity), None::<Velocity>);
+
+    let moving = Body {position: Position {x: 3, y: 4}, velocity: Velocity {x: 1, y: 0}};
+    moving.update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Velocity {x: 1, y: 0}));
+
+    // The velocity that was already written is left untouched
+    Body {position: Position {x: 5, y: 6}, velocity: Velocity::default()}.update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Position {x: 5, y: 6}));
+    assert_eq!(get(&world, entity), Some(Velocity {x: 1, y: 0}));
+
+    Ok(())
+}
+
+/// A position that is stored in the world as a `Position` component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point(i32, i32);
+
+impl From<Point> for Position {
+    fn from(Point(x, y): Point) -> Self {
+        Position {x, y}
+    }
+}
+
+impl From<Position> for Point {
+    fn from(Position {x, y}: Position) -> Self {
+        Point(x, y)
+    }
+}
+
+impl From<u32> for Health {
+    fn from(value: u32) -> Self {
+        Health(value)
+    }
+}
+
+impl From<Health> for u32 {
+    fn from(Health(value): Health) -> Self {
+        value
+    }
+}
+
+fn double_health(Health(value): Health) -> Health {
+    Health(value * 2)
+}
+
+#[test]
+fn read_with_transforms_on_read() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Boosted {
+        position: Position,
+        #[component_group(read_with = "double_health")]
+        health: Option<Health>,
+    }
+
+    let mut world = new_world();
+    let entity = Boosted {position: Position {x: 1, y: 2}, health: Some(Health(3))}.create(&mut world);
+    // The component is written unchanged
+    assert_eq!(get(&world, entity), Some(Health(3)));
+    // and transformed whenever it is read
+    assert_eq!(Boosted::from_world(&world, entity).health, Some(Health(6)));
+    assert_eq!(Boosted::first_from_world(&world).map(|(_, group)| group.health), Some(Some(Health(6))));
+    assert_eq!(Boosted::remove(&mut world, entity).health, Some(Health(6)));
+}
+
+#[test]
+fn fields_with_different_component_types() -> Result<(), specs::error::Error> {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Marker {
+        #[component_group(component = "Position")]
+        point: Point,
+        #[component_group(component = "Health")]
+        health: Option<u32>,
+    }
+
+    let mut world = new_world();
+    let entity = Marker {point: Point(1, 2), health: Some(3)}.create(&mut world);
+    // The real components are stored in the world
+    assert_eq!(get(&world, entity), Some(Position {x: 1, y: 2}));
+    assert_eq!(get(&world, entity), Some(Health(3)));
+    assert_eq!(Marker::from_world(&world, entity), Marker {point: Point(1, 2), health: Some(3)});
+
+    Marker {point: Point(4, 5), health: None}.update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Position {x: 4, y: 5}));
+    assert_eq!(get(&world, entity), None::<Health>);
+    assert_eq!(Marker::first_from_world(&world), Some((entity, Marker {point: Point(4, 5), health: None})));
+
+    assert_eq!(Marker::remove(&mut world, entity), Marker {point: Point(4, 5), health: None});
+    assert_eq!(get(&world, entity), None::<Position>);
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, Component, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Version(u64);
+
+impl Increment for Version {
+    fn increment(&mut self) {
+        self.0 += 1;
+    }
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+#[component_group(version = "Version")]
+struct Versioned {
+    position: Position,
+    health: Health,
+}
+
+#[test]
+fn version_bumped_on_update() -> Result<(), specs::error::Error> {
+    let mut world = new_world();
+    world.register::<Version>();
+
+    let group = Versioned {position: Position {x: 1, y: 2}, health: Health(3)};
+    let entity = group.clone().create(&mut world);
+    // Creating the group does not count as an update
+    assert_eq!(get(&world, entity), None::<Version>);
+
+    group.clone().update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Version(1)));
+    group.clone().update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Version(2)));
+
+    let other = group.clone().create(&mut world);
+    Versioned::update_many(&mut world, vec![(entity, group.clone()), (other, group.clone())])?;
+    assert_eq!(get(&world, entity), Some(Version(3)));
+    assert_eq!(get(&world, other), Some(Version(1)));
+
+    group.set_all(&mut world, &[entity, other])?;
+    assert_eq!(get(&world, entity), Some(Version(4)));
+    assert_eq!(get(&world, other), Some(Version(2)));
+    Ok(())
+}
+
+#[test]
+fn version_bumped_by_every_mutator() -> Result<(), specs::error::Error> {
+    let mut world = new_world();
+    world.register::<Version>();
+
+    let group = Versioned {position: Position {x: 1, y: 2}, health: Health(3)};
+    let entity = group.clone().create(&mut world);
+    let other = group.clone().create(&mut world);
+
+    group.clone().update_selected(&mut world, entity, VersionedFields {health: true, ..Default::default()})?;
+    assert_eq!(get(&world, entity), Some(Version(1)));
+    group.clone().try_update(&mut world, entity).map_err(|(_, err)| err)?;
+    assert_eq!(get(&world, entity), Some(Version(2)));
+    group.clone().update_stats(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Version(3)));
+    group.clone().update_with_events(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Version(4)));
+    group.clone().update_returning_removed(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Version(5)));
+
+    let mut values = HashMap::new();
+    values.insert("health".to_string(), VersionedFieldValue::Health(Health(7)));
+    Versioned::apply_fields(&mut world, entity, &values, false)?;
+    assert_eq!(get(&world, entity), Some(Version(6)));
+
+    Versioned::copy_health(&mut world, entity, other)?;
+    assert_eq!(get(&world, other), Some(Version(1)));
+    // Only the entity that was copied to is written
+    assert_eq!(get(&world, entity), Some(Version(6)));
+
+    #[cfg(feature = "parallel")]
+    {
+        group.par_set_all(&mut world)?;
+        assert_eq!(get(&world, entity), Some(Version(7)));
+        assert_eq!(get(&world, other), Some(Version(2)));
+    }
+    Ok(())
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+#[component_group(transparent)]
+struct Wrapped {
+    inner: Position,
+}
+
+#[test]
+fn transparent_reads_and_writes_component() -> Result<(), specs::error::Error> {
+    let mut world = new_world();
+    let entity = Wrapped {inner: Position {x: 1, y: 2}}.create(&mut world);
+    assert_eq!(get(&world, entity), Some(Position {x: 1, y: 2}));
+
+    let mut wrapped = Wrapped::from_world(&world, entity);
+    assert_eq!(*wrapped, Position {x: 1, y: 2});
+    wrapped.x = 5;
+    wrapped.update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Position {x: 5, y: 2}));
+
+    let removed = Wrapped::remove(&mut world, entity);
+    assert_eq!(removed.inner, Position {x: 5, y: 2});
+    assert_eq!(get(&world, entity), None::<Position>);
+    Ok(())
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct LabeledPlayer {
+    position: Position,
+    #[component_group(skip)]
+    label: String,
+    animation: Option<Animation>,
+}
+
+#[test]
+fn skipped_field_is_not_a_component() -> Result<(), specs::error::Error> {
+    let mut world = new_world();
+    let entity = LabeledPlayer {
+        position: Position {x: 1, y: 2},
+        label: "player one".to_string(),
+        animation: Some(Animation {frame: 3}),
+    }.create(&mut world);
+    assert_eq!(get(&world, entity), Some(Position {x: 1, y: 2}));
+
+    // The skipped field is filled in with its default value when the group is read
+    let expected = LabeledPlayer {
+        position: Position {x: 1, y: 2},
+        label: String::new(),
+        animation: Some(Animation {frame: 3}),
+    };
+    assert_eq!(LabeledPlayer::from_world(&world, entity), expected);
+    assert_eq!(LabeledPlayer::first_from_world(&world), Some((entity, expected.clone())));
+
+    LabeledPlayer {
+        position: Position {x: 4, y: 5},
+        label: "renamed".to_string(),
+        animation: None,
+    }.update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Position {x: 4, y: 5}));
+    assert_eq!(get(&world, entity), None::<Animation>);
+
+    let removed = LabeledPlayer::remove(&mut world, entity);
+    assert_eq!(removed, LabeledPlayer {position: Position {x: 4, y: 5}, label: String::new(), animation: None});
+    assert_eq!(get(&world, entity), None::<Position>);
+    Ok(())
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct RequiredPosition {
+    #[component_group(required)]
+    position: Option<Position>,
+    health: Health,
+}
+
+#[test]
+fn required_option_field_round_trip() -> Result<(), specs::error::Error> {
+    let mut world = new_world();
+    let group = RequiredPosition {position: Some(Position {x: 1, y: 2}), health: Health(3)};
+    let entity = group.clone().create(&mut world);
+    assert_eq!(get(&world, entity), Some(Position {x: 1, y: 2}));
+    assert_eq!(RequiredPosition::from_world(&world, entity), group);
+    assert_eq!(RequiredPosition::required_field_names(), &["position", "health"]);
+
+    // Entities without the component do not have the group
+    remove::<Position>(&mut world, entity);
+    assert_eq!(RequiredPosition::first_from_world(&world), None);
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "the `position` field is required, but it was None")]
+fn required_option_field_none_on_create() {
+    let mut world = new_world();
+    RequiredPosition {position: None, health: Health(3)}.create(&mut world);
+}
+
+#[derive(Debug)]
+pub enum GameError {
+    Storage(String),
+}
+
+fn storage_error(err: specs::error::Error) -> GameError {
+    GameError::Storage(err.to_string())
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+#[component_group(update_error = "GameError", map_err = "storage_error")]
+struct MappedErrors {
+    position: Position,
+    health: Health,
+}
+
+#[test]
+fn map_err_converts_update_errors() {
+    let mut world = new_world();
+    let group = MappedErrors {position: Position {x: 1, y: 2}, health: Health(3)};
+    let entity = group.clone().create(&mut world);
+    let result: Result<(), GameError> = group.clone().update(&mut world, entity);
+    assert!(result.is_ok());
+
+    world.delete_entity(entity).unwrap();
+    match group.clone().update(&mut world, entity) {
+        Err(GameError::Storage(message)) => assert!(message.contains("Wrong generation"), "{}", message),
+        Ok(()) => panic!("expected the update of a deleted entity to fail"),
+    }
+    match group.try_update(&mut world, entity) {
+        Err(("position", GameError::Storage(_))) => {},
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let mut values = HashMap::new();
+    values.insert("velocity".to_string(), MappedErrorsFieldValue::Health(Health(4)));
+    match MappedErrors::apply_fields(&mut world, entity, &values, false) {
+        Err(GameError::Storage(message)) => assert!(message.contains("not a field"), "{}", message),
+        Ok(()) => panic!("expected an unknown field name to fail"),
+    }
+}
+
+#[derive(Debug)]
+pub struct ConvertedError(specs::error::Error);
+
+impl From<specs::error::Error> for ConvertedError {
+    fn from(err: specs::error::Error) -> Self {
+        ConvertedError(err)
+    }
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+#[component_group(update_error = "ConvertedError")]
+struct ConvertedErrors {
+    position: Position,
+}
+
+#[test]
+fn update_error_converts_with_from() {
+    let mut world = new_world();
+    let group = ConvertedErrors {position: Position {x: 1, y: 2}};
+    let entity = group.clone().create(&mut world);
+    world.delete_entity(entity).unwrap();
+
+    let result: Result<(), ConvertedError> = group.update(&mut world, entity);
+    assert!(matches!(result, Err(ConvertedError(specs::error::Error::WrongGeneration(_)))));
+}
@@ -1,6 +1,8 @@
-use component_group::ComponentGroup;
+use std::collections::HashMap;
 
-use specs::{World, WorldExt, Entity, Component, VecStorage, HashMapStorage, NullStorage, ReadStorage, WriteStorage};
+use component_group::{ComponentGroup, ComponentNotRegistered, UpdateStats, assert_group_eq};
+
+use specs::{World, WorldExt, Builder, BitSet, Join, System, RunNow, Entities, Read, LazyUpdate, Entity, Component, VecStorage, HashMapStorage, NullStorage, ReadStorage, WriteStorage};
 use specs::error::Error as SpecsError;
 use specs_derive::Component;
 
@@ -530,3 +532,1469 @@ fn remove_optional_component_not_present() {
     assert_eq!(removed_player.health, Health(5));
     assert_eq!(removed_player.animation, None);
 }
+
+#[test]
+fn read_from_history() {
+    let mut frame0 = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create(&mut frame0);
+
+    // The next frame has the same entity with some changes
+    let mut frame1 = new_world();
+    let entity1 = PlayerComponents {
+        position: Position {x: 13, y: 59},
+        health: Health(4),
+        animation: Some(Animation {frame: 1}),
+    }.create(&mut frame1);
+    assert_eq!(entity, entity1);
+
+    let history = vec![frame0, frame1];
+
+    let player = PlayerComponents::at_frame(&history, 0, entity).unwrap();
+    assert_eq!(player.position, Position {x: 12, y: 59});
+    assert_eq!(player.animation, None);
+    let player = PlayerComponents::at_frame(&history, 1, entity).unwrap();
+    assert_eq!(player.position, Position {x: 13, y: 59});
+    assert_eq!(player.animation, Some(Animation {frame: 1}));
+
+    // Frames that are out of range return None
+    assert_eq!(PlayerComponents::at_frame(&history, 2, entity), None);
+
+    // Entities that are not alive in that frame return None
+    let mut history = history;
+    history[1].delete_entity(entity).unwrap();
+    history[1].maintain();
+    assert_eq!(PlayerComponents::at_frame(&history, 1, entity), None);
+    assert!(PlayerComponents::at_frame(&history, 0, entity).is_some());
+}
+
+#[test]
+fn update_many_entities() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..3).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world)).collect();
+
+    let updates: Vec<_> = entities.iter().enumerate().map(|(i, &entity)| (entity, PlayerComponents {
+        position: Position {x: -(i as i32), y: 10},
+        health: Health(i as u32),
+        animation: if i == 1 { None } else { Some(Animation {frame: i}) },
+    })).collect();
+    let expected = updates.clone();
+    PlayerComponents::update_many(&mut world, updates)?;
+
+    // Each entity should reflect its own values
+    for (entity, group) in expected {
+        assert_eq!(PlayerComponents::from_world(&world, entity), group);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn update_many_compared_to_update() -> Result<(), SpecsError> {
+    use std::time::Instant;
+
+    const ENTITIES: usize = 5000;
+    let groups: Vec<_> = (0..ENTITIES).map(|i| PlayerComponents {
+        position: Position {x: i as i32, y: 0},
+        health: Health(5),
+        animation: Some(Animation {frame: i}),
+    }).collect();
+    // Most of the updates remove the optional component
+    let updated = |i: usize| PlayerComponents {
+        position: Position {x: 0, y: i as i32},
+        health: Health(i as u32),
+        animation: if i < ENTITIES / 10 { Some(Animation {frame: 0}) } else { None },
+    };
+
+    let mut batch_world = new_world();
+    let batch_entities = PlayerComponents::create_many(&mut batch_world, groups.clone());
+    let mut single_world = new_world();
+    let single_entities = PlayerComponents::create_many(&mut single_world, groups);
+
+    let updates = batch_entities.iter().enumerate().map(|(i, &entity)| (entity, updated(i))).collect();
+    let start = Instant::now();
+    PlayerComponents::update_many(&mut batch_world, updates)?;
+    let batch_time = start.elapsed();
+
+    let start = Instant::now();
+    for (i, &entity) in single_entities.iter().enumerate() {
+        updated(i).update(&mut single_world, entity)?;
+    }
+    let single_time = start.elapsed();
+    println!("update_many: {:?}, update: {:?} ({} entities)", batch_time, single_time, ENTITIES);
+
+    // Both approaches should leave the worlds in the same state
+    for (i, (&batch_entity, &single_entity)) in batch_entities.iter().zip(&single_entities).enumerate() {
+        let group = PlayerComponents::from_world(&batch_world, batch_entity);
+        assert_eq!(group, updated(i));
+        assert_eq!(group, PlayerComponents::from_world(&single_world, single_entity));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn update_many_with_dead_entity() {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..3).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world)).collect();
+    world.delete_entity(entities[1]).unwrap();
+
+    let updates = entities.iter().map(|&entity| (entity, PlayerComponents {
+        position: Position {x: 0, y: 0},
+        health: Health(1),
+        animation: None,
+    })).collect();
+    assert!(PlayerComponents::update_many(&mut world, updates).is_err());
+
+    // The entities before the dead one are still updated, just like calling update in order
+    assert_eq!(get(&world, entities[0]), Some(Health(1)));
+    assert_eq!(get(&world, entities[2]), Some(Health(5)));
+}
+
+#[test]
+fn apply_named_fields() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+
+    let mut values = HashMap::new();
+    values.insert("health".to_string(), PlayerComponentsFieldValue::Health(Health(20)));
+    PlayerComponents::apply_fields(&mut world, entity, &values, false)?;
+    // Only the named field should change
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(20),
+        animation: Some(Animation {frame: 2}),
+    });
+
+    values.insert("speed".to_string(), PlayerComponentsFieldValue::Animation(None));
+    assert!(PlayerComponents::apply_fields(&mut world, entity, &values, false).is_err());
+    PlayerComponents::apply_fields(&mut world, entity, &values, true)?;
+
+    // A value for the wrong field is always an error and nothing is written
+    let mut values = HashMap::new();
+    values.insert("animation".to_string(), PlayerComponentsFieldValue::Animation(None));
+    values.insert("position".to_string(), PlayerComponentsFieldValue::Health(Health(1)));
+    assert!(PlayerComponents::apply_fields(&mut world, entity, &values, true).is_err());
+    assert_eq!(get(&world, entity), Some(Animation {frame: 2}));
+
+    values.remove("position");
+    PlayerComponents::apply_fields(&mut world, entity, &values, true)?;
+    assert_eq!(get(&world, entity), None::<Animation>);
+
+    Ok(())
+}
+
+#[test]
+fn read_multiple_without_cloning() {
+    let mut world = new_world();
+    let entity1 = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+    let entity2 = PlayerComponents {
+        position: Position {x: -10, y: 78},
+        health: Health(230),
+        animation: None,
+    }.create(&mut world);
+
+    // Both entities can be read by reference from a single fetch of the storages
+    let read = PlayerComponents::read_world(&world);
+    assert_eq!(read.position(entity1), Some(&Position {x: 12, y: 59}));
+    assert_eq!(read.health(entity1), Some(&Health(5)));
+    assert_eq!(read.animation(entity1), Some(&Animation {frame: 2}));
+    assert_eq!(read.position(entity2), Some(&Position {x: -10, y: 78}));
+    assert_eq!(read.health(entity2), Some(&Health(230)));
+    assert_eq!(read.animation(entity2), None);
+}
+
+#[test]
+fn try_update_reports_failing_field() {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world);
+
+    // Updating a deleted entity fails on the very first component
+    world.delete_entity(entity).unwrap();
+    let player = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(3),
+        animation: None,
+    };
+    match player.try_update(&mut world, entity) {
+        Err((field, SpecsError::WrongGeneration(_))) => assert_eq!(field, "position"),
+        res => panic!("expected the update to fail, got: {:?}", res),
+    }
+}
+
+#[test]
+fn reconcile_snapshot() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let existing = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+    let mut id_map = HashMap::new();
+    id_map.insert(1, existing);
+
+    let mut snapshot = HashMap::new();
+    snapshot.insert(1, PlayerComponents {
+        position: Position {x: 0, y: 1},
+        health: Health(4),
+        animation: None,
+    });
+    snapshot.insert(2, PlayerComponents {
+        position: Position {x: -3, y: 7},
+        health: Health(20),
+        animation: Some(Animation {frame: 9}),
+    });
+    let expected = snapshot.clone();
+    PlayerComponents::reconcile(snapshot, &mut id_map, &mut world)?;
+
+    // The existing id should still map to the same entity and a new entity should be created
+    assert_eq!(id_map.len(), 2);
+    assert_eq!(id_map[&1], existing);
+    assert_ne!(id_map[&2], existing);
+    for (id, group) in expected {
+        assert_eq!(PlayerComponents::from_world(&world, id_map[&id]), group);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn create_preserving_entity_id() {
+    let mut source = new_world();
+    let entities: Vec<_> = (0..3).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(5),
+        animation: None,
+    }.create(&mut source)).collect();
+
+    let mut world = new_world();
+    let player = PlayerComponents::from_world(&source, entities[2]);
+    let entity = player.clone().create_preserving_id(entities[2], &mut world);
+    assert_eq!(entity.id(), entities[2].id());
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+    // None of the entities that were allocated to reach that id are left behind
+    assert_eq!(PlayerComponents::first_from_world(&world).map(|(entity, _)| entity), Some(entity));
+    assert_eq!(world.entities().join().count(), 1);
+
+    // An id that is already taken cannot be preserved
+    let other = player.clone().create_preserving_id(entities[2], &mut world);
+    assert_ne!(other.id(), entities[2].id());
+    assert_eq!(PlayerComponents::from_world(&world, other), player);
+}
+
+#[test]
+fn create_preserving_id_reuses_deleted_id() {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..12).map(|_| world.create_entity().build()).collect();
+    // The id of the most recently deleted entity is reused first, so a larger id than the
+    // requested one is allocated before the requested id
+    world.delete_entity(entities[5]).unwrap();
+    world.delete_entity(entities[10]).unwrap();
+
+    let player = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(3),
+        animation: None,
+    };
+    let entity = player.clone().create_preserving_id(entities[5], &mut world);
+    assert_eq!(entity.id(), entities[5].id());
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+    assert_eq!(world.entities().join().count(), 11);
+
+    // Ids that are far past every id used so far are not searched for
+    let mut source_world = World::new();
+    let source = (0..2000).map(|_| source_world.create_entity().build()).last().unwrap();
+    let mut other = new_world();
+    other.create_entity().build();
+    let entity = player.create_preserving_id(source, &mut other);
+    assert_ne!(entity.id(), source.id());
+    assert_eq!(other.entities().join().count(), 2);
+}
+
+#[test]
+fn names_of_present_components() {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+    remove::<Health>(&mut world, entity);
+
+    assert_eq!(PlayerComponents::component_names_present(&world, entity), vec!["Position", "Animation"]);
+}
+
+#[test]
+fn entity_matches_group() {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world);
+    assert!(PlayerComponents::matches(&world, entity));
+
+    remove::<Health>(&mut world, entity);
+    assert!(!PlayerComponents::matches(&world, entity));
+
+    // Health is not registered in this world, so fetching its storage would panic. The missing
+    // position means that storage is never fetched.
+    let mut world = World::new();
+    world.register::<Position>();
+    let entity = world.create_entity().build();
+    assert!(!PlayerComponents::matches(&world, entity));
+}
+
+#[test]
+fn set_all_entities() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..3).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world)).collect();
+    let other = PlayerComponents {
+        position: Position {x: 100, y: 100},
+        health: Health(1),
+        animation: Some(Animation {frame: 0}),
+    }.create(&mut world);
+
+    let player = PlayerComponents {
+        position: Position {x: -4, y: 7},
+        health: Health(9),
+        animation: None,
+    };
+    player.clone().set_all(&mut world, &entities)?;
+
+    for &entity in &entities {
+        assert_eq!(PlayerComponents::from_world(&world, entity), player);
+    }
+    // Entities that were not in the list are left untouched
+    assert_eq!(get(&world, other), Some(Health(1)));
+    assert_eq!(get(&world, other), Some(Animation {frame: 0}));
+
+    Ok(())
+}
+
+#[test]
+fn find_with_predicate() {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..4).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world)).collect();
+
+    let (entity, player) = PlayerComponents::find_from_world(&world, |entity, _| entity.id() % 2 == 1)
+        .expect("expected an entity with an odd id");
+    assert_eq!(entity, entities[1]);
+    assert_eq!(player.position, Position {x: 1, y: 1});
+
+    // The group is passed to the predicate as well
+    let found = PlayerComponents::find_from_world(&world, |_, player| player.position.x > 10);
+    assert_eq!(found, None);
+}
+
+#[test]
+fn create_many_in_order() {
+    let mut world = new_world();
+    let players: Vec<_> = (0..5).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(i as u32),
+        animation: None,
+    }).collect();
+    let entities = PlayerComponents::create_many_ordered(&mut world, players.clone()).unwrap();
+
+    for (i, (&entity, player)) in entities.iter().zip(players).enumerate() {
+        assert_eq!(entity.id(), entities[0].id() + i as u32);
+        assert_eq!(PlayerComponents::from_world(&world, entity), player);
+    }
+}
+
+#[test]
+fn update_returns_removed_fields() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+
+    let player = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(3),
+        animation: None,
+    };
+    let removed = player.clone().update_returning_removed(&mut world, entity)?;
+    assert_eq!(removed, vec!["animation"]);
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+
+    // Nothing is removed the second time because the animation is already gone
+    let removed = player.update_returning_removed(&mut world, entity)?;
+    assert!(removed.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn update_only_selected_fields() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+
+    let selected = PlayerComponentsFields {health: true, ..Default::default()};
+    PlayerComponents {
+        position: Position {x: 0, y: 0},
+        health: Health(1),
+        animation: None,
+    }.update_selected(&mut world, entity, selected)?;
+
+    // Only health was written. The animation was not removed even though it was None.
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(1),
+        animation: Some(Animation {frame: 2}),
+    });
+
+    let updated = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(3),
+        animation: None,
+    };
+    updated.clone().update_selected(&mut world, entity, PlayerComponentsFields::all())?;
+    assert_eq!(PlayerComponents::from_world(&world, entity), updated);
+
+    Ok(())
+}
+
+#[test]
+fn edit_first_in_place() {
+    let mut world = new_world();
+    assert!(!PlayerComponents::edit_first(&mut world, |_, _, _, _| panic!("no groups to edit")));
+
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world);
+
+    let found = PlayerComponents::edit_first(&mut world, |edited, _, health, animation| {
+        assert_eq!(edited, entity);
+        assert_eq!(animation, None);
+        health.0 += 10;
+    });
+    assert!(found);
+    assert_eq!(get(&world, entity), Some(Health(15)));
+}
+
+#[test]
+fn from_world_in_bitset() {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..4).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world)).collect();
+    // Entities without all of the required components are left out even if they are in the mask
+    remove::<Health>(&mut world, entities[3]);
+
+    let mut mask = BitSet::new();
+    mask.add(entities[0].id());
+    mask.add(entities[2].id());
+    mask.add(entities[3].id());
+    let groups = PlayerComponents::from_world_filtered(&world, &mask);
+    let found: Vec<_> = groups.iter().map(|(entity, player)| (*entity, player.position.x)).collect();
+    assert_eq!(found, vec![(entities[0], 0), (entities[2], 2)]);
+}
+
+#[test]
+fn from_world_first_missing_field() {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    };
+    let entity = player.clone().create(&mut world);
+    assert_eq!(PlayerComponents::from_world_first_missing(&world, entity), Ok(player));
+
+    remove::<Health>(&mut world, entity);
+    assert_eq!(PlayerComponents::from_world_first_missing(&world, entity), Err("health"));
+    // The first missing field is reported even if later ones are missing too
+    remove::<Position>(&mut world, entity);
+    assert_eq!(PlayerComponents::from_world_first_missing(&world, entity), Err("position"));
+}
+
+#[test]
+fn join_with_extra_storage() {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..4).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world)).collect();
+    insert(&mut world, entities[1], NotInGroup);
+    insert(&mut world, entities[3], NotInGroup);
+    // Entities without all of the required components are left out even if they are in the join
+    remove::<Health>(&mut world, entities[3]);
+    let other = world.create_entity().with(NotInGroup).build();
+
+    let not_in_group = world.read_storage::<NotInGroup>();
+    let joined = PlayerComponents::join_with(&world, &not_in_group);
+    let found: Vec<_> = joined.iter().map(|(entity, player, &tag)| (*entity, player.position.x, tag)).collect();
+    assert_eq!(found, vec![(entities[1], 1, NotInGroup)]);
+    assert!(joined.iter().all(|(entity, _, _)| *entity != other));
+}
+
+#[test]
+fn required_and_optional_field_names() {
+    assert_eq!(PlayerComponents::required_field_names(), &["position", "health"]);
+    assert_eq!(PlayerComponents::optional_field_names(), &["animation"]);
+}
+
+#[test]
+fn type_names_of_fields() {
+    assert_eq!(PlayerComponents::field_type_name("position"), Some("Position"));
+    assert_eq!(PlayerComponents::field_type_name("animation"), Some("Animation"));
+    assert_eq!(PlayerComponents::field_type_name("velocity"), None);
+}
+
+mod shapes {
+    use specs::{Component, VecStorage};
+    use specs_derive::Component;
+
+    #[derive(Debug, Clone, Component, PartialEq, Eq)]
+    #[storage(VecStorage)]
+    pub struct Shape {pub sides: u32}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tagged<T>(T);
+
+impl<T: Send + Sync + 'static> Component for Tagged<T> {
+    type Storage = VecStorage<Self>;
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct QualifiedTypes {
+    shape: shapes::Shape,
+    tag: Option<Tagged<u32>>,
+}
+
+#[test]
+fn type_names_of_qualified_and_generic_fields() {
+    assert_eq!(QualifiedTypes::field_type_name("shape"), Some("shapes::Shape"));
+    assert_eq!(QualifiedTypes::field_type_name("tag"), Some("Tagged<u32>"));
+
+    let mut world = World::new();
+    world.register::<shapes::Shape>();
+    let group = QualifiedTypes {shape: shapes::Shape {sides: 3}, tag: Some(Tagged(1))};
+    let entity = world.create_entity().build();
+    match group.clone().update(&mut world, entity).unwrap_err() {
+        SpecsError::Custom(err) => {
+            let err = err.0.downcast_ref::<ComponentNotRegistered>().unwrap();
+            assert_eq!(*err, ComponentNotRegistered("Tagged<u32>"));
+        },
+        err => panic!("unexpected error: {}", err),
+    }
+
+    world.register::<Tagged<u32>>();
+    group.update(&mut world, entity).unwrap();
+    assert_eq!(QualifiedTypes::component_names_present(&world, entity), vec!["shapes::Shape", "Tagged<u32>"]);
+}
+
+#[test]
+fn optional_fields_by_name() {
+    assert_eq!(PlayerComponents::field_is_optional("position"), Some(false));
+    assert_eq!(PlayerComponents::field_is_optional("health"), Some(false));
+    assert_eq!(PlayerComponents::field_is_optional("animation"), Some(true));
+    assert_eq!(PlayerComponents::field_is_optional("velocity"), None);
+}
+
+#[test]
+fn optional_fields_by_index() {
+    assert_eq!(PlayerComponents::FIELD_OPTIONAL, [false, false, true]);
+}
+
+#[test]
+fn extend_seeded_builder() {
+    let mut world = new_world();
+    let builder = world.create_entity().with(NotInGroup);
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.extend_builder(builder).build();
+
+    assert_eq!(get(&world, entity), Some(NotInGroup));
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    });
+}
+
+#[test]
+fn lazy_build_in_system() {
+    struct SpawnPlayer(Option<Entity>);
+
+    impl<'a> System<'a> for SpawnPlayer {
+        type SystemData = (Entities<'a>, Read<'a, LazyUpdate>);
+
+        fn run(&mut self, (entities, lazy): Self::SystemData) {
+            let entity = PlayerComponents {
+                position: Position {x: 12, y: 59},
+                health: Health(5),
+                animation: Some(Animation {frame: 2}),
+            }.lazy_build(lazy.create_entity(&entities));
+            self.0 = Some(entity);
+        }
+    }
+
+    let mut world = new_world();
+    let mut system = SpawnPlayer(None);
+    system.run_now(&world);
+    let entity = system.0.unwrap();
+    // Nothing is added until the world is maintained
+    assert_eq!(get(&world, entity), None::<Position>);
+
+    world.maintain();
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    });
+}
+
+#[test]
+fn attach_lazy_in_system() {
+    struct SpawnPlayer(Option<Entity>);
+
+    impl<'a> System<'a> for SpawnPlayer {
+        type SystemData = (Entities<'a>, Read<'a, LazyUpdate>);
+
+        fn run(&mut self, (entities, lazy): Self::SystemData) {
+            let entity = entities.create();
+            PlayerComponents {
+                position: Position {x: 12, y: 59},
+                health: Health(5),
+                animation: None,
+            }.attach_lazy(entity, &lazy);
+            self.0 = Some(entity);
+        }
+    }
+
+    let mut world = new_world();
+    let mut system = SpawnPlayer(None);
+    system.run_now(&world);
+    let entity = system.0.unwrap();
+    // The entity can be used right away, but nothing is added until the world is maintained
+    assert_eq!(get(&world, entity), None::<Position>);
+
+    world.maintain();
+    assert!(world.is_alive(entity));
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    });
+}
+
+#[test]
+fn assert_matches_world_when_equal() {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    };
+    let entity = player.clone().create(&mut world);
+
+    player.assert_matches_world(&world, entity);
+}
+
+#[test]
+#[should_panic(expected = "the `animation` field does not match the world")]
+fn assert_matches_world_when_different() {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    };
+    let entity = player.clone().create(&mut world);
+    insert(&mut world, entity, Animation {frame: 3});
+
+    player.assert_matches_world(&world, entity);
+}
+
+#[test]
+fn assert_group_eq_when_equal() {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+
+    assert_group_eq!(world, entity, PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    });
+}
+
+#[test]
+#[should_panic(expected = "the `health` field does not match the world")]
+fn assert_group_eq_when_different() {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world);
+
+    assert_group_eq!(world, entity, PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(6),
+        animation: None,
+    });
+}
+
+#[test]
+fn create_chained_with_extra_component() {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create_chained(&mut world)
+        .with_extra(|entity, world| insert(world, entity, NotInGroup))
+        .with_extra(|entity, world| insert(world, entity, Animation {frame: 3}))
+        .entity();
+
+    assert_eq!(get(&world, entity), Some(Position {x: 12, y: 59}));
+    assert_eq!(get(&world, entity), Some(NotInGroup));
+    assert_eq!(get(&world, entity), Some(Animation {frame: 3}));
+}
+
+#[test]
+fn create_and_read_back() {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    };
+    let (entity, read_player) = player.clone().create_and_read(&mut world);
+
+    assert_eq!(read_player, player);
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+}
+
+#[test]
+fn create_and_update_optional_groups() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    };
+
+    assert_eq!(PlayerComponents::create_opt(None, &mut world), None);
+    assert_eq!(PlayerComponents::first_from_world(&world), None);
+
+    let entity = PlayerComponents::create_opt(Some(player.clone()), &mut world)
+        .expect("expected an entity to be created");
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+
+    // Updating with None leaves the entity untouched
+    PlayerComponents::update_opt(None, &mut world, entity)?;
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+
+    let updated = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(3),
+        animation: Some(Animation {frame: 4}),
+    };
+    PlayerComponents::update_opt(Some(updated.clone()), &mut world, entity)?;
+    assert_eq!(PlayerComponents::from_world(&world, entity), updated);
+
+    Ok(())
+}
+
+#[test]
+fn update_skipped_after_concurrent_change() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world);
+
+    let expected = PlayerComponents::from_world(&world, entity);
+    let mut healed = expected.clone();
+    healed.health = Health(10);
+
+    // Some other system moves the entity after it was read
+    world.write_storage::<Position>().insert(entity, Position {x: 0, y: 0})?;
+
+    assert!(!healed.clone().update_if_unchanged(&mut world, entity, &expected)?);
+    assert_eq!(get(&world, entity), Some(Health(5)));
+    assert_eq!(get(&world, entity), Some(Position {x: 0, y: 0}));
+
+    // Reading again picks up the change and allows the update to go through
+    let expected = PlayerComponents::from_world(&world, entity);
+    let mut healed = expected.clone();
+    healed.health = Health(10);
+    assert!(healed.clone().update_if_unchanged(&mut world, entity, &expected)?);
+    assert_eq!(PlayerComponents::from_world(&world, entity), healed);
+
+    Ok(())
+}
+
+#[test]
+fn remove_and_cleanup_empty_entities() {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    };
+    let entity1 = player.clone().create(&mut world);
+    let entity2 = player.clone().create(&mut world);
+    insert(&mut world, entity2, NotInGroup);
+
+    let is_empty = |world: &World, entity| get::<NotInGroup>(world, entity).is_none();
+    assert_eq!(PlayerComponents::remove_and_cleanup(&mut world, entity1, is_empty), player);
+    assert_eq!(PlayerComponents::remove_and_cleanup(&mut world, entity2, is_empty), player);
+
+    // Only the entity that had nothing left is deleted
+    assert!(!world.is_alive(entity1));
+    assert!(world.is_alive(entity2));
+    assert_eq!(get(&world, entity2), Some(NotInGroup));
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn par_set_all_matching_entities() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..100).map(|i| PlayerComponents {
+        position: Position {x: i, y: i},
+        health: Health(i as u32),
+        animation: if i % 2 == 0 { Some(Animation {frame: 2}) } else { None },
+    }.create(&mut world)).collect();
+    // Does not have all the required components, so it should not be modified
+    let partial = entities[10];
+    remove::<Health>(&mut world, partial);
+
+    let player = PlayerComponents {
+        position: Position {x: -4, y: 7},
+        health: Health(9),
+        animation: Some(Animation {frame: 0}),
+    };
+    player.clone().par_set_all(&mut world)?;
+
+    for &entity in &entities {
+        if entity == partial {
+            assert_eq!(get(&world, entity), Some(Position {x: 10, y: 10}));
+            assert_eq!(get(&world, entity), Some(Animation {frame: 2}));
+        } else {
+            assert_eq!(PlayerComponents::from_world(&world, entity), player);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn extract_columns() {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..4).map(|i| PlayerComponents {
+        position: Position {x: i, y: -i},
+        health: Health(i as u32),
+        animation: if i % 2 == 0 { Some(Animation {frame: i as usize}) } else { None },
+    }.create(&mut world)).collect();
+    // Does not have all the required components, so it should not be extracted
+    remove::<Health>(&mut world, entities[3]);
+
+    let columns = PlayerComponents::columns_from_world(&world);
+    assert_eq!(columns.entities, &entities[..3]);
+    assert_eq!(columns.position, vec![Position {x: 0, y: 0}, Position {x: 1, y: -1}, Position {x: 2, y: -2}]);
+    assert_eq!(columns.health, vec![Health(0), Health(1), Health(2)]);
+    assert_eq!(columns.animation, vec![Some(Animation {frame: 0}), None, Some(Animation {frame: 2})]);
+}
+
+#[test]
+fn clone_and_compare_columns() {
+    let mut world = new_world();
+    for i in 0..3 {
+        PlayerComponents {
+            position: Position {x: i, y: -i},
+            health: Health(i as u32),
+            animation: None,
+        }.create(&mut world);
+    }
+
+    let columns = PlayerComponents::columns_from_world(&world);
+    let mut cloned = columns.clone();
+    assert_eq!(cloned, columns);
+    assert!(format!("{:?}", cloned).starts_with("PlayerComponentsColumns { entities: ["));
+
+    cloned.health[1] = Health(100);
+    assert_ne!(cloned, columns);
+}
+
+#[test]
+fn columns_round_trip() {
+    let mut world = new_world();
+    for i in 0..4 {
+        PlayerComponents {
+            position: Position {x: i, y: -i},
+            health: Health(i as u32),
+            animation: if i % 2 == 0 { Some(Animation {frame: i as usize}) } else { None },
+        }.create(&mut world);
+    }
+    let columns = PlayerComponents::columns_from_world(&world);
+
+    let mut world2 = new_world();
+    let entities = PlayerComponents::create_from_columns(columns, &mut world2);
+    assert_eq!(entities.len(), 4);
+
+    let columns = PlayerComponents::columns_from_world(&world);
+    let columns2 = PlayerComponents::columns_from_world(&world2);
+    assert_eq!(columns2.entities, entities);
+    assert_eq!(columns2.position, columns.position);
+    assert_eq!(columns2.health, columns.health);
+    assert_eq!(columns2.animation, columns.animation);
+}
+
+// The copy method generated for the `__group` field is not snake case
+#[allow(non_snake_case)]
+mod marked {
+    use std::marker::PhantomData;
+
+    use component_group::ComponentGroup;
+
+    use super::{Position, Health};
+
+    // The companion structs hold a marker of the group, which must not clash with these fields
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    pub struct MarkerNames<T: Send + Sync + 'static> {
+        pub __group: Position,
+        pub health: Option<Health>,
+        pub kind: PhantomData<T>,
+    }
+}
+
+#[test]
+fn companion_markers_do_not_clash_with_fields() {
+    use marked::{MarkerNames, MarkerNamesColumns};
+
+    let mut world = new_world();
+    let entity = MarkerNames::<u8> {
+        __group: Position {x: 1, y: 2},
+        health: None,
+        kind: Default::default(),
+    }.create(&mut world);
+
+    let read = MarkerNames::<u8>::read_world(&world);
+    assert_eq!(read.__group(entity), Some(&Position {x: 1, y: 2}));
+    drop(read);
+
+    // The marker of the columns is private, so they are created from outside of the module with
+    // the generated constructor
+    let columns = MarkerNamesColumns::<u8>::new(
+        vec![entity],
+        vec![Position {x: 1, y: 2}],
+        vec![None],
+    );
+    assert_eq!(MarkerNames::<u8>::columns_from_world(&world), columns);
+    assert_eq!(MarkerNames::<u8>::create_from_columns(columns.clone(), &mut world).len(), 1);
+    assert_eq!(columns.__group, vec![Position {x: 1, y: 2}]);
+}
+
+#[test]
+#[should_panic(expected = "expected the `health` column to have 2 rows")]
+fn create_from_mismatched_columns() {
+    let mut world = new_world();
+    let columns = PlayerComponentsColumns {
+        entities: Vec::new(),
+        position: vec![Position {x: 0, y: 0}, Position {x: 1, y: 1}],
+        health: vec![Health(1)],
+        animation: vec![None, None],
+    };
+    PlayerComponents::create_from_columns(columns, &mut world);
+}
+
+#[test]
+fn migrate_to_larger_group() -> Result<(), SpecsError> {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct PlayerComponentsV1 {
+        position: Position,
+        health: Health,
+    }
+
+    let mut world = new_world();
+    let entity = PlayerComponentsV1 {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+    }.create(&mut world);
+
+    component_group::migrate(&mut world, entity, |old: PlayerComponentsV1| PlayerComponents {
+        position: old.position,
+        health: Health(old.health.0 * 2),
+        animation: Some(Animation {frame: 0}),
+    })?;
+
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(10),
+        animation: Some(Animation {frame: 0}),
+    });
+
+    Ok(())
+}
+
+#[test]
+fn remove_audited_lists_removed_fields() {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    };
+    let entity = player.clone().create(&mut world);
+
+    let (removed, audit) = PlayerComponents::remove_audited(&mut world, entity);
+    assert_eq!(removed, player);
+    // The animation was never present, so it was not removed
+    assert_eq!(audit, vec!["position", "health"]);
+    assert_eq!(get(&world, entity), None::<Position>);
+    assert_eq!(get(&world, entity), None::<Health>);
+}
+
+#[test]
+fn copy_group_to_another_world() {
+    let mut source = new_world();
+    let mut dest = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    };
+    let entity = player.clone().create(&mut source);
+    insert(&mut source, entity, NotInGroup);
+
+    let copied = PlayerComponents::copy_to_world(&source, entity, &mut dest);
+    assert_eq!(PlayerComponents::from_world(&dest, copied), player);
+    // Absent optional components and components outside of the group are not copied
+    assert_eq!(get(&dest, copied), None::<Animation>);
+    assert_eq!(get(&dest, copied), None::<NotInGroup>);
+    // The source entity is left untouched
+    assert_eq!(PlayerComponents::from_world(&source, entity), player);
+    assert_eq!(get(&source, entity), Some(NotInGroup));
+}
+
+#[test]
+fn update_if_alive_skips_deleted_entity() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    };
+    let entity = player.clone().create(&mut world);
+
+    assert!(PlayerComponents {health: Health(6), ..player.clone()}.update_if_alive(&mut world, entity)?);
+    assert_eq!(get(&world, entity), Some(Health(6)));
+
+    world.delete_entity(entity).unwrap();
+    assert!(!player.update_if_alive(&mut world, entity)?);
+    assert_eq!(get(&world, entity), None::<Health>);
+    Ok(())
+}
+
+#[test]
+fn clear_optional_components() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+    insert(&mut world, entity, NotInGroup);
+
+    PlayerComponents::clear_optionals(&mut world, entity)?;
+    assert_eq!(get(&world, entity), None::<Animation>);
+    assert_eq!(get(&world, entity), Some(Position {x: 12, y: 59}));
+    assert_eq!(get(&world, entity), Some(Health(5)));
+    // only components in the group are removed
+    assert_eq!(get(&world, entity), Some(NotInGroup));
+
+    // clearing again does nothing since the optional components are already gone
+    PlayerComponents::clear_optionals(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Health(5)));
+
+    world.delete_entity(entity).unwrap();
+    assert!(PlayerComponents::clear_optionals(&mut world, entity).is_err());
+    Ok(())
+}
+
+#[test]
+fn copy_single_field() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let source = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world);
+    let target = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(100),
+        animation: Some(Animation {frame: 7}),
+    }.create(&mut world);
+
+    PlayerComponents::copy_health(&mut world, source, target)?;
+    assert_eq!(PlayerComponents::from_world(&world, target), PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(5),
+        animation: Some(Animation {frame: 7}),
+    });
+
+    // The source did not have an animation, so copying it removes the one on the target
+    PlayerComponents::copy_animation(&mut world, source, target)?;
+    assert_eq!(get(&world, target), None::<Animation>);
+    assert_eq!(get(&world, target), Some(Position {x: 1, y: 2}));
+    // The source is never modified
+    assert_eq!(get(&world, source), Some(Health(5)));
+    Ok(())
+}
+
+#[test]
+fn update_stats_counts_changes() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entity = world.create_entity().with(Position {x: 1, y: 2}).build();
+
+    let stats = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.update_stats(&mut world, entity)?;
+    // The position was overwritten with an equal value, so it does not count as modified
+    assert_eq!(stats, UpdateStats {inserted: 2, modified: 0, removed: 0});
+
+    let stats = PlayerComponents {
+        position: Position {x: 3, y: 2},
+        health: Health(5),
+        animation: None,
+    }.update_stats(&mut world, entity)?;
+    assert_eq!(stats, UpdateStats {inserted: 0, modified: 1, removed: 1});
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents {
+        position: Position {x: 3, y: 2},
+        health: Health(5),
+        animation: None,
+    });
+    Ok(())
+}
+
+#[test]
+fn first_from_world_sorted_lowest_id() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entities: Vec<_> = (0..4).map(|_| world.create_entity().build()).collect();
+    // Add the group to the entities in a different order than they were created in
+    for &i in &[3, 1, 2] {
+        PlayerComponents {
+            position: Position {x: i as i32, y: 0},
+            health: Health(i as u32),
+            animation: None,
+        }.update(&mut world, entities[i])?;
+    }
+
+    let (entity, group) = PlayerComponents::first_from_world_sorted(&world).unwrap();
+    assert_eq!(entity, entities[1]);
+    assert_eq!(group.health, Health(1));
+
+    // The id of a deleted entity is reused by the next one that is created
+    world.delete_entity(entities[0]).unwrap();
+    world.maintain();
+    let reused = PlayerComponents {
+        position: Position {x: 0, y: 0},
+        health: Health(0),
+        animation: None,
+    }.create(&mut world);
+    assert_eq!(reused.id(), entities[0].id());
+
+    let (entity, _) = PlayerComponents::first_from_world_sorted(&world).unwrap();
+    assert_eq!(entity, reused);
+    Ok(())
+}
+
+#[test]
+fn take_all_groups_from_world() {
+    let mut world = new_world();
+    let player1 = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    };
+    let player2 = PlayerComponents {
+        position: Position {x: 3, y: 4},
+        health: Health(8),
+        animation: None,
+    };
+    let entity1 = player1.clone().create(&mut world);
+    let entity2 = player2.clone().create(&mut world);
+    // missing a required component, so it does not have the group
+    let partial = world.create_entity().with(Position {x: 1, y: 1}).with(NotInGroup).build();
+    insert(&mut world, entity1, NotInGroup);
+
+    let taken = PlayerComponents::take_all_from_world(&mut world);
+    assert_eq!(taken, vec![(entity1, player1), (entity2, player2)]);
+
+    assert_eq!(PlayerComponents::first_from_world(&world), None);
+    assert!(!PlayerComponents::matches(&world, entity1));
+    assert!(!PlayerComponents::matches(&world, entity2));
+    // only components in the group are removed
+    assert_eq!(get(&world, entity1), Some(NotInGroup));
+    assert_eq!(get(&world, partial), Some(Position {x: 1, y: 1}));
+
+    assert!(PlayerComponents::take_all_from_world(&mut world).is_empty());
+}
+
+#[test]
+fn take_all_optional_groups_from_world() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Extras {
+        position: Option<Position>,
+        animation: Option<Animation>,
+    }
+
+    let mut world = new_world();
+    let extras = Extras {
+        position: None,
+        animation: Some(Animation {frame: 2}),
+    };
+    let entity = extras.clone().create(&mut world);
+    // has none of the components in the group, so it does not have the group
+    let other = world.create_entity().with(Health(3)).build();
+
+    let taken = Extras::take_all_from_world(&mut world);
+    assert_eq!(taken, vec![(entity, extras)]);
+    assert_eq!(get(&world, entity), None::<Animation>);
+    assert_eq!(get(&world, other), Some(Health(3)));
+
+    assert!(Extras::take_all_from_world(&mut world).is_empty());
+}
+
+#[test]
+fn setup_fresh_world() {
+    // None of the components are registered in this world
+    let mut world = World::new();
+    PlayerComponents::setup(&mut world);
+    // Registering again does not replace the existing storages
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    };
+    let entity = player.clone().create(&mut world);
+    PlayerComponents::setup(&mut world);
+
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+}
+
+#[test]
+fn update_freshly_built_entity() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let entity = world.create_entity().with(NotInGroup).build();
+
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    };
+    player.clone().update(&mut world, entity)?;
+
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+    assert_eq!(get(&world, entity), Some(NotInGroup));
+    Ok(())
+}
+
+// World can only be sent between threads when the parallel feature of specs is enabled
+#[cfg(feature = "parallel")]
+#[test]
+fn create_in_shared_world() -> Result<(), SpecsError> {
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+
+    let world = Arc::new(RwLock::new(new_world()));
+    let threads: Vec<_> = (0..2).map(|i| {
+        let world = Arc::clone(&world);
+        thread::spawn(move || {
+            PlayerComponents {
+                position: Position {x: i, y: i},
+                health: Health(i as u32),
+                animation: None,
+            }.create_shared(&world)
+        })
+    }).collect();
+    let entities: Vec<_> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+
+    PlayerComponents {
+        position: Position {x: 5, y: 5},
+        health: Health(5),
+        animation: Some(Animation {frame: 1}),
+    }.update_shared(&world, entities[0])?;
+
+    let world = world.read().unwrap();
+    assert_eq!(PlayerComponents::from_world(&world, entities[0]).health, Health(5));
+    assert_eq!(PlayerComponents::from_world(&world, entities[1]).health, Health(1));
+    Ok(())
+}
+
+#[test]
+fn update_unregistered_component() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+    let entity = world.create_entity().build();
+
+    let err = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.update(&mut world, entity).unwrap_err();
+    assert_not_registered(err);
+    // Nothing is written when a component is not registered
+    assert_eq!(get(&world, entity), None::<Position>);
+}
+
+fn assert_not_registered(err: SpecsError) {
+    match err {
+        SpecsError::Custom(err) => {
+            let err = err.0.downcast_ref::<ComponentNotRegistered>().unwrap();
+            assert_eq!(*err, ComponentNotRegistered("Animation"));
+        },
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn every_writer_returns_unregistered_component() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+    let entity = world.create_entity().build();
+    let other = world.create_entity().build();
+    let group = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    };
+
+    assert_not_registered(PlayerComponents::update_many(&mut world, vec![(entity, group.clone())]).unwrap_err());
+    assert_not_registered(group.clone().set_all(&mut world, &[entity]).unwrap_err());
+    assert_not_registered(group.clone().update_selected(&mut world, entity, PlayerComponentsFields::all()).unwrap_err());
+    let (name, err) = group.clone().try_update(&mut world, entity).unwrap_err();
+    assert_eq!(name, "animation");
+    assert_not_registered(err);
+    assert_not_registered(group.clone().update_stats(&mut world, entity).unwrap_err());
+    assert_not_registered(group.clone().update_with_events(&mut world, entity).unwrap_err());
+    assert_not_registered(group.clone().update_returning_removed(&mut world, entity).unwrap_err());
+    let mut values = HashMap::new();
+    values.insert("health".to_string(), PlayerComponentsFieldValue::Health(Health(20)));
+    assert_not_registered(PlayerComponents::apply_fields(&mut world, entity, &values, false).unwrap_err());
+    assert_not_registered(PlayerComponents::copy_animation(&mut world, entity, other).unwrap_err());
+    assert_not_registered(PlayerComponents::clear_optionals(&mut world, entity).unwrap_err());
+    #[cfg(feature = "parallel")]
+    assert_not_registered(group.clone().par_set_all(&mut world).unwrap_err());
+
+    // Nothing is written when a component is not registered
+    assert_eq!(get(&world, entity), None::<Position>);
+    assert_eq!(get(&world, entity), None::<Health>);
+}
+
+#[test]
+#[should_panic(expected = "the Animation component was not registered in the world")]
+fn remove_unregistered_component() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+    let entity = world.create_entity().build();
+
+    PlayerComponents::remove(&mut world, entity);
+}
+
+#[test]
+#[should_panic(expected = "the Animation component was not registered in the world")]
+fn create_unregistered_component() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+
+    // The storage of an optional field that is None is never used
+    PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: None,
+    }.create(&mut world);
+
+    PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 1}),
+    }.create(&mut world);
+}
+
+#[test]
+fn read_then_update_other_entity() -> Result<(), SpecsError> {
+    let mut world = new_world();
+    let leader = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+    let follower = PlayerComponents {
+        position: Position {x: 0, y: 0},
+        health: Health(1),
+        animation: None,
+    }.create(&mut world);
+
+    PlayerComponents::read_then(&mut world, leader, |group, world| {
+        PlayerComponents {animation: None, ..group}.update(world, follower)
+    })?;
+
+    assert_eq!(get(&world, follower), Some(Position {x: 12, y: 59}));
+    assert_eq!(get(&world, follower), Some(Health(5)));
+    assert_eq!(get(&world, follower), None::<Animation>);
+    // The entity that was read is left unchanged
+    assert_eq!(get(&world, leader), Some(Animation {frame: 2}));
+    Ok(())
+}
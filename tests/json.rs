@@ -0,0 +1,70 @@
+#![cfg(feature = "serde_json")]
+
+use component_group::ComponentGroup;
+
+use specs::{World, WorldExt, Component, VecStorage, HashMapStorage};
+use specs_derive::Component;
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, Component, PartialEq, Eq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Health(u32);
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct PlayerComponents {
+    position: Position,
+    health: Health,
+    animation: Option<Animation>,
+}
+
+#[test]
+fn json_round_trip() -> Result<(), serde_json::Error> {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+    world.register::<Animation>();
+
+    let entity = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut world);
+
+    let value = PlayerComponents::from_world(&world, entity).to_json();
+    assert_eq!(value, json!({
+        "position": {"x": 12, "y": 59},
+        "health": 5,
+        "animation": {"frame": 2},
+    }));
+    assert_eq!(PlayerComponents::from_json(&value)?, PlayerComponents::from_world(&world, entity));
+
+    Ok(())
+}
+
+#[test]
+fn json_missing_fields() -> Result<(), serde_json::Error> {
+    // Optional fields may be left out
+    let group = PlayerComponents::from_json(&json!({
+        "position": {"x": 1, "y": 2},
+        "health": 3,
+    }))?;
+    assert_eq!(group.animation, None);
+    assert_eq!(group.to_json()["animation"], serde_json::Value::Null);
+
+    // Required fields may not
+    let err = PlayerComponents::from_json(&json!({"position": {"x": 1, "y": 2}})).unwrap_err();
+    assert!(err.to_string().contains("health"));
+
+    assert!(PlayerComponents::from_json(&json!([1, 2, 3])).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+// Tests that a group with only optional fields can be created when every field is None without
+// producing any warnings
+
+#![deny(warnings)]
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage, HashMapStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(ComponentGroup)]
+pub struct Extras {
+    position: Option<Position>,
+    animation: Option<Animation>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Animation>();
+    let entity = Extras {
+        position: None,
+        animation: None,
+    }.create(&mut world);
+
+    // The entity exists but has none of the components
+    assert!(world.is_alive(entity));
+    assert!(Extras::component_names_present(&world, entity).is_empty());
+    let extras = Extras::from_world(&world, entity);
+    assert_eq!(extras.position, None);
+    assert_eq!(extras.animation, None);
+}
@@ -0,0 +1,59 @@
+// Tests that a where clause with a bound on an associated type is carried over to every
+// generated impl
+
+#![deny(warnings)]
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Sword {damage: u32}
+
+pub trait Equipment {
+    type Item;
+}
+
+pub struct Warrior;
+
+impl Equipment for Warrior {
+    type Item = Sword;
+}
+
+#[derive(ComponentGroup)]
+pub struct Equipped<E>
+    where E: Equipment + Send + Sync + 'static,
+          E::Item: Component + Clone + Send + Sync,
+{
+    position: Position,
+    item: E::Item,
+    backup: Option<<E as Equipment>::Item>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Sword>();
+
+    let entity = Equipped::<Warrior> {
+        position: Position {x: 1, y: 2},
+        item: Sword {damage: 3},
+        backup: None,
+    }.create(&mut world);
+
+    let equipped = Equipped::<Warrior>::from_world(&world, entity);
+    assert_eq!(equipped.item, Sword {damage: 3});
+    let columns = Equipped::<Warrior>::columns_from_world(&world);
+    assert_eq!(columns.item, vec![Sword {damage: 3}]);
+    let read = Equipped::<Warrior>::read_world(&world);
+    assert_eq!(read.position(entity), Some(&Position {x: 1, y: 2}));
+}
@@ -0,0 +1,44 @@
+// Tests that a group of components that do not implement Clone can be used to create, update,
+// and remove components when the Clone bound is disabled
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage, HashMapStorage, ReadStorage};
+use specs_derive::Component;
+
+#[derive(Debug, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(ComponentGroup, Debug, PartialEq)]
+#[component_group(clone_bound = false)]
+pub struct Sprite {
+    position: Position,
+    animation: Option<Animation>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Animation>();
+    let entity = Sprite {
+        position: Position {x: 1, y: 2},
+        animation: Some(Animation {frame: 3}),
+    }.create(&mut world);
+
+    Sprite {
+        position: Position {x: 4, y: 5},
+        animation: None,
+    }.update(&mut world, entity).unwrap();
+    assert_eq!(world.system_data::<ReadStorage<Position>>().get(entity), Some(&Position {x: 4, y: 5}));
+
+    let sprite = Sprite::remove(&mut world, entity);
+    assert_eq!(sprite, Sprite {position: Position {x: 4, y: 5}, animation: None});
+}
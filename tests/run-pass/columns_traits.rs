@@ -0,0 +1,29 @@
+// Tests that the columns of a group only need to implement the standard traits that are used
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+// Only implements Clone, so the columns can be cloned but not compared or printed
+#[derive(Clone, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(ComponentGroup)]
+pub struct PlayerComponents {
+    position: Position,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    PlayerComponents {position: Position {x: 1, y: 2}}.create(&mut world);
+
+    let columns = PlayerComponents::columns_from_world(&world).clone();
+    assert_eq!(columns.entities.len(), 1);
+    assert_eq!(columns.position[0].x + columns.position[0].y, 3);
+}
@@ -0,0 +1,32 @@
+// Tests that the alias attribute avoids clashes with the names of generated companion types
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+// Would clash with the type generated for PlayerComponents without the alias
+pub struct PlayerComponentsRead;
+
+#[derive(ComponentGroup)]
+#[component_group(alias = "Player")]
+pub struct PlayerComponents {
+    position: Position,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    let entity = PlayerComponents {position: Position {x: 1, y: 2}}.create(&mut world);
+
+    let _existing = PlayerComponentsRead;
+    let read: PlayerRead = PlayerComponents::read_world(&world);
+    assert_eq!(read.position(entity), Some(&Position {x: 1, y: 2}));
+}
@@ -0,0 +1,57 @@
+// Tests that the crate attribute allows the derive to be used through a re-export when the
+// component_group crate itself is not available by name
+
+#![deny(warnings)]
+
+// Renamed so that paths starting with `component_group::` do not resolve
+extern crate component_group as engine_internal;
+extern crate specs;
+extern crate specs_derive;
+
+mod my_engine {
+    pub mod ecs {
+        pub use engine_internal::*;
+    }
+}
+
+use my_engine::ecs::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage, HashMapStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(ComponentGroup)]
+#[component_group(crate = "my_engine::ecs", warn_on_remove)]
+pub struct PlayerComponents {
+    position: Position,
+    animation: Option<Animation>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Animation>();
+    let entity = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        animation: Some(Animation {frame: 3}),
+    }.create(&mut world);
+
+    PlayerComponents {
+        position: Position {x: 4, y: 5},
+        animation: None,
+    }.update(&mut world, entity).unwrap();
+    PlayerComponents {
+        position: Position {x: 6, y: 7},
+        animation: None,
+    }.try_update(&mut world, entity).unwrap();
+
+    let group = PlayerComponents::from_world(&world, entity);
+    assert_eq!(group.position, Position {x: 6, y: 7});
+    assert_eq!(group.animation, None);
+}
@@ -2,7 +2,7 @@ extern crate component_group;
 extern crate specs;
 extern crate specs_derive;
 
-use component_group::ComponentGroup;
+use component_group::{ComponentGroup, ReadableGroup};
 use specs::{World, WorldExt, Builder, Entity, Entities, Component, VecStorage, ReadStorage, WriteStorage, Join};
 use specs::error::Error as SpecsError;
 use specs_derive::Component;
@@ -37,10 +37,8 @@ impl From<SpecsError> for InvalidUpdate {
     }
 }
 
-impl ComponentGroup for PlayerComponents {
-    type UpdateError = InvalidUpdate;
-
-    fn first_from_world(world: &World) -> Option<(Entity, Self)> {
+impl ReadableGroup for PlayerComponents {
+    fn read_first_from_world(world: &World) -> Option<(Entity, Self)> {
         // Needs to be updated every time the struct changes
         let (entities, positions, velocities, healths) = world.system_data::<(
             Entities,
@@ -57,7 +55,7 @@ impl ComponentGroup for PlayerComponents {
             }))
     }
 
-    fn from_world(world: &World, entity: Entity) -> Self {
+    fn read_from_world(world: &World, entity: Entity) -> Self {
         // Needs to be updated every time the struct changes
         let (positions, velocities, healths) = world.system_data::<(
             ReadStorage<Position>,
@@ -75,6 +73,10 @@ impl ComponentGroup for PlayerComponents {
                 .expect("expected a Health component to be present"),
         }
     }
+}
+
+impl ComponentGroup for PlayerComponents {
+    type UpdateError = InvalidUpdate;
 
     fn create(self, world: &mut World) -> Entity {
         // It's possible to write this code so that the compiler will at the very least
@@ -130,6 +132,20 @@ impl ComponentGroup for PlayerComponents {
                 .expect("expected a Health component to be present"),
         }
     }
+
+    fn component_names_present(world: &World, entity: Entity) -> Vec<&'static str> {
+        // Needs to be updated every time the struct changes
+        let (positions, velocities, healths) = world.system_data::<(
+            ReadStorage<Position>,
+            ReadStorage<Velocity>,
+            ReadStorage<Health>,
+        )>();
+        let mut names = Vec::new();
+        if positions.contains(entity) { names.push("Position"); }
+        if velocities.contains(entity) { names.push("Velocity"); }
+        if healths.contains(entity) { names.push("Health"); }
+        names
+    }
 }
 
 fn main() {}
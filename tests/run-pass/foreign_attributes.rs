@@ -0,0 +1,49 @@
+// Tests that attributes meant for other derives and tools are ignored by the derive
+
+#![deny(warnings)]
+
+extern crate component_group;
+extern crate serde;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use serde::{Serialize, Deserialize};
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Health(u32);
+
+// The serde feature of component_group implements Serialize and Deserialize for every group, so
+// they can only be derived when that feature is disabled
+#[derive(Debug, Clone, PartialEq, ComponentGroup)]
+#[cfg_attr(not(feature = "serde"), derive(Serialize, Deserialize))]
+#[cfg_attr(not(feature = "serde"), serde(rename_all = "camelCase", deny_unknown_fields))]
+pub struct PlayerComponents {
+    #[cfg_attr(not(feature = "serde"), serde(rename = "pos"))]
+    /// Documentation is an attribute too
+    position: Position,
+    #[cfg_attr(not(feature = "serde"), serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(test, allow(unused))]
+    #[allow(dead_code)]
+    health: Option<Health>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+
+    let player = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: None,
+    };
+    let entity = player.clone().create(&mut world);
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+}
@@ -0,0 +1,55 @@
+// Tests that a generic type parameter with a default type can be used in a group
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Velocity {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Spin(f32);
+
+#[derive(ComponentGroup)]
+pub struct Group<V: Send + Sync + Component + Clone = Velocity> {
+    position: Position,
+    velocity: V,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Velocity>();
+    world.register::<Spin>();
+
+    // Uses the default type parameter
+    let moving: Group = Group {
+        position: Position {x: 1, y: 2},
+        velocity: Velocity {x: 3, y: 4},
+    };
+    let entity1 = moving.create(&mut world);
+
+    // Overrides the default type parameter
+    let spinning: Group<Spin> = Group {
+        position: Position {x: 5, y: 6},
+        velocity: Spin(1.5),
+    };
+    let entity2 = spinning.create(&mut world);
+
+    assert_eq!(Group::<Velocity>::from_world(&world, entity1).velocity, Velocity {x: 3, y: 4});
+    assert_eq!(Group::<Spin>::from_world(&world, entity2).velocity, Spin(1.5));
+    let columns: GroupColumns = Group::columns_from_world(&world);
+    assert_eq!(columns.entities, vec![entity1]);
+    let read: GroupRead<Spin> = Group::read_world(&world);
+    assert_eq!(read.velocity(entity2), Some(&Spin(1.5)));
+}
@@ -0,0 +1,44 @@
+// Tests that a generic type parameter can be used only inside an Option
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage, HashMapStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(ComponentGroup)]
+pub struct Group<A: Send + Sync + Component + Clone> {
+    position: Position,
+    aux: Option<A>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Animation>();
+    let entity1 = Group {
+        position: Position {x: 1, y: 2},
+        aux: Some(Animation {frame: 3}),
+    }.create(&mut world);
+    let entity2 = Group::<Animation> {
+        position: Position {x: 4, y: 5},
+        aux: None,
+    }.create(&mut world);
+
+    let (first, group) = Group::<Animation>::first_from_world(&world).unwrap();
+    assert_eq!(first, entity1);
+    assert_eq!(group.aux, Some(Animation {frame: 3}));
+    assert_eq!(Group::<Animation>::from_world(&world, entity2).aux, None);
+    let removed = Group::<Animation>::remove(&mut world, entity1);
+    assert_eq!(removed.aux, Some(Animation {frame: 3}));
+}
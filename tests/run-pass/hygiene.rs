@@ -0,0 +1,43 @@
+// Tests that the bindings used by the generated code do not collide with user names
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Velocity {x: i32, y: i32}
+
+fn main() {
+    let __entities = "not the entities of the world";
+
+    // Field names that match the names of bindings used internally by the derive
+    #[derive(ComponentGroup)]
+    struct Movement {
+        __entities: Position,
+        __entity: Option<Velocity>,
+    }
+
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Velocity>();
+    let entity = Movement {
+        __entities: Position {x: 1, y: 2},
+        __entity: Some(Velocity {x: 3, y: 4}),
+    }.create(&mut world);
+
+    let (first, movement) = Movement::first_from_world(&world).unwrap();
+    assert_eq!(first, entity);
+    assert_eq!(movement.__entities, Position {x: 1, y: 2});
+    assert_eq!(movement.__entity, Some(Velocity {x: 3, y: 4}));
+    assert!(Movement::find_from_world(&world, |_, movement| movement.__entity.is_some()).is_some());
+    assert_eq!(__entities, "not the entities of the world");
+}
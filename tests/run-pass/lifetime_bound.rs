@@ -0,0 +1,54 @@
+// Tests that a lifetime parameter and the bounds that refer to it are carried over to every
+// generated impl, even if the lifetime is only used by a PhantomData field
+
+#![deny(warnings)]
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use std::marker::PhantomData;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Sword {damage: u32}
+
+#[derive(ComponentGroup)]
+pub struct Borrowed<'a, T: 'a + Component + Clone + Send + Sync> {
+    position: Position,
+    item: T,
+    extra: Option<T>,
+    level: PhantomData<&'a ()>,
+}
+
+fn read_item<'a>(world: &'a World, entity: specs::Entity) -> Borrowed<'a, Sword> {
+    Borrowed::from_world(world, entity)
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Sword>();
+
+    let entity = Borrowed::<Sword> {
+        position: Position {x: 1, y: 2},
+        item: Sword {damage: 3},
+        extra: None,
+        level: PhantomData,
+    }.create(&mut world);
+
+    let borrowed = read_item(&world, entity);
+    assert_eq!(borrowed.item, Sword {damage: 3});
+    let columns = Borrowed::<Sword>::columns_from_world(&world);
+    assert_eq!(columns.item, vec![Sword {damage: 3}]);
+    let read = Borrowed::<Sword>::read_world(&world);
+    assert_eq!(read.position(entity), Some(&Position {x: 1, y: 2}));
+}
@@ -0,0 +1,48 @@
+// Tests that fields can be explicitly marked as optional
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage, HashMapStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Shield(u32);
+
+#[derive(ComponentGroup)]
+pub struct PlayerComponents {
+    position: Position,
+    #[component_group(optional)]
+    animation: Option<Animation>,
+    // Marking a field with a qualified path to Option is allowed too
+    #[component_group(optional)]
+    shield: std::option::Option<Shield>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Animation>();
+    world.register::<Shield>();
+    let entity = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        animation: None,
+        shield: None,
+    }.create(&mut world);
+
+    let player = PlayerComponents::from_world(&world, entity);
+    assert_eq!(player.animation, None);
+    assert_eq!(player.shield, None);
+    assert_eq!(PlayerComponents::optional_field_names(), &["animation", "shield"]);
+}
@@ -0,0 +1,38 @@
+// Tests that PhantomData fields are skipped without needing to be marked
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use std::marker::PhantomData;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+// Selects behaviour but is not a component
+pub struct Flying;
+
+#[derive(ComponentGroup)]
+pub struct Group<M> {
+    position: Position,
+    _marker: PhantomData<M>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    let entity = Group::<Flying> {
+        position: Position {x: 1, y: 2},
+        _marker: PhantomData,
+    }.create(&mut world);
+
+    let group = Group::<Flying>::from_world(&world, entity);
+    assert_eq!(group.position, Position {x: 1, y: 2});
+    let read = Group::<Flying>::read_world(&world);
+    assert_eq!(read.position(entity), Some(&Position {x: 1, y: 2}));
+}
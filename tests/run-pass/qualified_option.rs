@@ -0,0 +1,74 @@
+// Tests that optional fields are detected when Option is written with a path to std or core
+
+#![deny(warnings)]
+
+extern crate core;
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage, HashMapStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Shield(u32);
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(HashMapStorage)]
+pub struct Speed(u32);
+
+mod custom {
+    use specs::{Component, VecStorage};
+
+    /// A type named Option that is not the Option from std
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Option<T>(pub T);
+
+    impl<T: Send + Sync + 'static> Component for Option<T> {
+        type Storage = VecStorage<Self>;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, ComponentGroup)]
+pub struct PlayerComponents {
+    position: Position,
+    animation: Option<Animation>,
+    shield: std::option::Option<Shield>,
+    speed: ::core::option::Option<Speed>,
+    // Only Option from std or core is treated as optional
+    level: custom::Option<u32>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Animation>();
+    world.register::<Shield>();
+    world.register::<Speed>();
+    world.register::<custom::Option<u32>>();
+
+    assert_eq!(PlayerComponents::required_field_names(), &["position", "level"]);
+    assert_eq!(PlayerComponents::optional_field_names(), &["animation", "shield", "speed"]);
+
+    let player = PlayerComponents {
+        position: Position {x: 1, y: 2},
+        animation: None,
+        shield: Some(Shield(3)),
+        speed: None,
+        level: custom::Option(4),
+    };
+    let entity = player.clone().create(&mut world);
+    assert_eq!(PlayerComponents::from_world(&world, entity), player);
+    assert!(!world.read_storage::<Animation>().contains(entity));
+    assert!(!world.read_storage::<Speed>().contains(entity));
+}
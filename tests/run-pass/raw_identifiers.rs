@@ -0,0 +1,47 @@
+// Tests that fields named with raw identifiers work and are named without the `r#` prefix
+
+#![deny(warnings)]
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Kind(u8);
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, PartialEq, ComponentGroup)]
+pub struct Item {
+    r#type: Kind,
+    r#move: Option<Position>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Kind>();
+    world.register::<Position>();
+
+    let item = Item {r#type: Kind(1), r#move: Some(Position {x: 1, y: 2})};
+    let entity = item.clone().create(&mut world);
+    assert_eq!(Item::from_world(&world, entity), item);
+
+    assert_eq!(Item::required_field_names(), &["type"]);
+    assert_eq!(Item::optional_field_names(), &["move"]);
+    assert_eq!(Item::field_is_optional("move"), Some(true));
+    assert_eq!(Item::field_is_optional("r#move"), None);
+
+    let different = Item {r#type: Kind(2), ..item};
+    let panic = panic::catch_unwind(AssertUnwindSafe(|| different.assert_matches_world(&world, entity))).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("the `type` field does not match the world"), "{}", message);
+}
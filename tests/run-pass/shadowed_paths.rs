@@ -0,0 +1,62 @@
+// Tests that the generated code uses absolute paths, so it still works inside of a module where
+// the names `specs` and `component_group` refer to something else
+
+#![deny(warnings)]
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use specs::{World, WorldExt};
+
+macro_rules! group_module {
+    ($name:ident) => {
+        pub mod $name {
+            // Shadow both crates with modules that contain nothing
+            #[allow(dead_code)]
+            mod specs {}
+            #[allow(dead_code)]
+            mod component_group {}
+
+            use ::component_group::ComponentGroup;
+            use ::specs::{Component, VecStorage};
+            use specs_derive::Component;
+
+            #[derive(Debug, Clone, PartialEq, Component)]
+            #[storage(VecStorage)]
+            pub struct Position {x: i32, y: i32}
+
+            #[derive(Debug, Clone, PartialEq, Component)]
+            #[storage(VecStorage)]
+            pub struct Health(u32);
+
+            #[derive(Debug, Clone, PartialEq, ComponentGroup)]
+            pub struct PlayerComponents {
+                position: Position,
+                health: Option<Health>,
+            }
+
+            impl PlayerComponents {
+                pub fn new(x: i32, y: i32) -> Self {
+                    PlayerComponents {position: Position {x, y}, health: Some(Health(3))}
+                }
+            }
+        }
+    };
+}
+
+group_module!(game);
+
+use component_group::ComponentGroup;
+use game::{PlayerComponents, Position, Health};
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+
+    let entity = PlayerComponents::new(1, 2).create(&mut world);
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents::new(1, 2));
+    PlayerComponents::new(3, 4).update(&mut world, entity).unwrap();
+    assert_eq!(PlayerComponents::remove(&mut world, entity), PlayerComponents::new(3, 4));
+}
@@ -0,0 +1,41 @@
+// Tests that a transparent group derefs to its only field and still works as a group
+
+#![deny(warnings)]
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt, Component, VecStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(ComponentGroup)]
+#[component_group(transparent)]
+pub struct Wrapped {
+    inner: Position,
+}
+
+#[derive(ComponentGroup)]
+#[component_group(transparent)]
+pub struct MaybeWrapped {
+    inner: Option<Position>,
+}
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+
+    let entity = Wrapped {inner: Position {x: 1, y: 2}}.create(&mut world);
+    let mut wrapped = Wrapped::from_world(&world, entity);
+    assert_eq!(*wrapped, Position {x: 1, y: 2});
+    wrapped.y = 3;
+    wrapped.update(&mut world, entity).unwrap();
+
+    let maybe = MaybeWrapped::from_world(&world, entity);
+    assert_eq!(maybe.as_ref(), Some(&Position {x: 1, y: 3}));
+}
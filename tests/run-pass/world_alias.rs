@@ -0,0 +1,49 @@
+// Tests that the generated code does not depend on which World type the `specs` path in the
+// deriving module resolves to. A different World type (e.g. from another version of specs) used to
+// produce "incompatible type for trait" errors.
+
+#![deny(warnings)]
+
+extern crate component_group;
+extern crate specs;
+extern crate specs_derive;
+
+mod game {
+    mod specs {
+        /// Stands in for the World type of a different version of specs
+        #[allow(dead_code)]
+        pub struct World;
+    }
+
+    use component_group::ComponentGroup;
+    use ::specs::{Component, VecStorage};
+    use specs_derive::Component;
+
+    #[derive(Debug, Clone, PartialEq, Component)]
+    #[storage(VecStorage)]
+    pub struct Position {x: i32, y: i32}
+
+    #[derive(Debug, Clone, PartialEq, ComponentGroup)]
+    pub struct PlayerComponents {
+        pub position: Position,
+    }
+
+    impl PlayerComponents {
+        pub fn new(x: i32, y: i32) -> Self {
+            PlayerComponents {position: Position {x, y}}
+        }
+    }
+}
+
+use component_group::ComponentGroup;
+use specs::{World, WorldExt};
+
+use game::{PlayerComponents, Position};
+
+fn main() {
+    let mut world = World::new();
+    world.register::<Position>();
+
+    let entity = PlayerComponents::new(1, 2).create(&mut world);
+    assert_eq!(PlayerComponents::from_world(&world, entity), PlayerComponents::new(1, 2));
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "serde")]
+
+use component_group::ComponentGroup;
+
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+use specs::{World, WorldExt, Component, VecStorage, HashMapStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, Component, PartialEq, Eq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Health(u32);
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct PlayerComponents {
+    position: Position,
+    health: Health,
+    animation: Option<Animation>,
+}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct GenericComponents<T: Component + Clone + Send + Sync> {
+    position: Position,
+    extra: T,
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+    world.register::<Animation>();
+    world
+}
+
+#[test]
+fn serialize_between_worlds() -> Result<(), serde_json::Error> {
+    let mut world = new_world();
+    let player = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    };
+    let entity = player.clone().create(&mut world);
+
+    let json = serde_json::to_string(&PlayerComponents::from_world(&world, entity))?;
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&json)?, json!({
+        "position": {"x": 12, "y": 59},
+        "health": 5,
+        "animation": {"frame": 2},
+    }));
+
+    let mut remote = new_world();
+    let group: PlayerComponents = serde_json::from_str(&json)?;
+    let remote_entity = group.create(&mut remote);
+    assert_eq!(PlayerComponents::from_world(&remote, remote_entity), player);
+    Ok(())
+}
+
+#[test]
+fn deserialize_missing_fields() {
+    let group: PlayerComponents = serde_json::from_value(json!({
+        "position": {"x": 1, "y": 2},
+        "health": 3,
+        "unknown": true,
+    })).unwrap();
+    assert_eq!(group, PlayerComponents {
+        position: Position {x: 1, y: 2},
+        health: Health(3),
+        animation: None,
+    });
+
+    // Required fields must be present
+    let err = serde_json::from_value::<PlayerComponents>(json!({"position": {"x": 1, "y": 2}})).unwrap_err();
+    assert_eq!(err.to_string(), "missing field `health`");
+}
+
+#[test]
+fn serialize_generic_group() -> Result<(), serde_json::Error> {
+    let group = GenericComponents {position: Position {x: 1, y: 2}, extra: Health(7)};
+    let value = serde_json::to_value(&group)?;
+    assert_eq!(value, json!({"position": {"x": 1, "y": 2}, "extra": 7}));
+    assert_eq!(serde_json::from_value::<GenericComponents<Health>>(value)?, group);
+    Ok(())
+}
@@ -0,0 +1,431 @@
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use component_group::{ComponentGroup, EventKind};
+
+use specs::{World, WorldExt, Entity, Component, VecStorage, DenseVecStorage, DefaultVecStorage, NullStorage, FlaggedStorage, ReadStorage, WriteStorage};
+use specs::storage::{UnprotectedStorage, ComponentEvent};
+use specs::world::Index;
+use specs::hibitset::BitSetLike;
+use specs::error::Error as SpecsError;
+use specs_derive::Component;
+
+thread_local! {
+    /// Every storage operation performed by a LoggedStorage on this thread
+    static STORAGE_LOG: RefCell<Vec<(&'static str, &'static str)>> = const { RefCell::new(Vec::new()) };
+}
+
+fn log(component: &'static str, operation: &'static str) {
+    STORAGE_LOG.with(|storage_log| storage_log.borrow_mut().push((component, operation)));
+}
+
+/// Returns and clears the storage operations logged so far on this thread
+fn take_log() -> Vec<(&'static str, &'static str)> {
+    STORAGE_LOG.with(|storage_log| storage_log.borrow_mut().drain(..).collect())
+}
+
+/// Records every insertion, modification, and removal so the order of operations across storages
+/// can be observed
+pub struct LoggedStorage<T: Named>(DenseVecStorage<T>, PhantomData<T>);
+
+pub trait Named {
+    const NAME: &'static str;
+}
+
+impl<T: Named> Default for LoggedStorage<T> {
+    fn default() -> Self {
+        LoggedStorage(Default::default(), PhantomData)
+    }
+}
+
+impl<T: Named> UnprotectedStorage<T> for LoggedStorage<T> {
+    unsafe fn clean<B: BitSetLike>(&mut self, has: B) {
+        self.0.clean(has)
+    }
+
+    unsafe fn get(&self, id: Index) -> &T {
+        self.0.get(id)
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut T {
+        log(T::NAME, "modify");
+        self.0.get_mut(id)
+    }
+
+    unsafe fn insert(&mut self, id: Index, value: T) {
+        log(T::NAME, "insert");
+        self.0.insert(id, value)
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> T {
+        log(T::NAME, "remove");
+        self.0.remove(id)
+    }
+}
+
+macro_rules! logged_component {
+    ($name:ident) => {
+        impl Named for $name {
+            const NAME: &'static str = stringify!($name);
+        }
+
+        impl Component for $name {
+            type Storage = FlaggedStorage<Self, LoggedStorage<Self>>;
+        }
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mass(u32);
+logged_component!(Mass);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acceleration(i32);
+logged_component!(Acceleration);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drag(u32);
+logged_component!(Drag);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spin(i32);
+logged_component!(Spin);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Buoyancy(u32);
+logged_component!(Buoyancy);
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct Physics {
+    mass: Mass,
+    drag: Option<Drag>,
+    acceleration: Acceleration,
+}
+
+fn physics_world() -> World {
+    let mut world = World::new();
+    world.register::<Mass>();
+    world.register::<Acceleration>();
+    world.register::<Drag>();
+    world.register::<Spin>();
+    world.register::<Buoyancy>();
+    world
+}
+
+#[derive(Debug, Clone, Component, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, Copy, Default, Component, PartialEq, Eq)]
+#[storage(DefaultVecStorage)]
+pub struct Velocity {x: i32, y: i32}
+
+#[derive(Debug, Clone, Copy, Default, Component, PartialEq, Eq)]
+#[storage(NullStorage)]
+pub struct Frozen;
+
+fn get<C: Component + Clone>(world: &World, entity: Entity) -> Option<C> {
+    let storage = world.system_data::<ReadStorage<C>>();
+    storage.get(entity).cloned()
+}
+
+#[test]
+fn default_vec_storage() -> Result<(), SpecsError> {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Movement {
+        position: Position,
+        velocity: Velocity,
+    }
+
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Velocity>();
+
+    let entity = Movement {
+        position: Position {x: 1, y: 2},
+        velocity: Velocity {x: 3, y: 4},
+    }.create(&mut world);
+    assert_eq!(Movement::from_world(&world, entity).velocity, Velocity {x: 3, y: 4});
+
+    Movement {
+        position: Position {x: 1, y: 2},
+        velocity: Velocity {x: 0, y: 0},
+    }.update(&mut world, entity)?;
+
+    // A component that happens to be equal to the default value is still present and removable
+    let removed = Movement::remove(&mut world, entity);
+    assert_eq!(removed.velocity, Velocity {x: 0, y: 0});
+    assert_eq!(get(&world, entity), None::<Velocity>);
+
+    // The default value left behind in the storage is not observable afterwards
+    assert!(Movement::first_from_world(&world).is_none());
+
+    Ok(())
+}
+
+#[test]
+fn optional_null_storage_tag() -> Result<(), SpecsError> {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Statue {
+        position: Position,
+        // Some means the tag is present, None means it is absent
+        #[component_group(tag)]
+        frozen: Option<Frozen>,
+    }
+
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Frozen>();
+
+    let entity = Statue {
+        position: Position {x: 1, y: 2},
+        frozen: Some(Frozen),
+    }.create(&mut world);
+    assert_eq!(get(&world, entity), Some(Frozen));
+    assert_eq!(Statue::from_world(&world, entity).frozen, Some(Frozen));
+
+    Statue {
+        position: Position {x: 1, y: 2},
+        frozen: None,
+    }.update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), None::<Frozen>);
+    assert_eq!(Statue::from_world(&world, entity).frozen, None);
+
+    // Updating with the tag again should add it back
+    Statue {
+        position: Position {x: 3, y: 4},
+        frozen: Some(Frozen),
+    }.update(&mut world, entity)?;
+    assert_eq!(Statue::from_world(&world, entity), Statue {
+        position: Position {x: 3, y: 4},
+        frozen: Some(Frozen),
+    });
+
+    // Updating a tag that is already present leaves it present
+    Statue {
+        position: Position {x: 3, y: 4},
+        frozen: Some(Frozen),
+    }.update(&mut world, entity)?;
+    assert_eq!(get(&world, entity), Some(Frozen));
+
+    let removed = Statue::remove(&mut world, entity);
+    assert_eq!(removed.frozen, Some(Frozen));
+    assert_eq!(get(&world, entity), None::<Frozen>);
+
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "expected a Velocity component to be present")]
+fn default_vec_storage_removed_twice() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Movement {
+        position: Position,
+        velocity: Velocity,
+    }
+
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Velocity>();
+
+    let entity = Movement {
+        position: Position {x: 1, y: 2},
+        velocity: Velocity {x: 3, y: 4},
+    }.create(&mut world);
+
+    // The storage still holds a default value in this slot after the removal
+    world.system_data::<WriteStorage<Velocity>>().remove(entity);
+    // remove must not return that default value
+    Movement::remove(&mut world, entity);
+}
+
+#[test]
+fn update_reports_events() -> Result<(), SpecsError> {
+    let mut world = physics_world();
+
+    let entity = Physics {
+        mass: Mass(10),
+        drag: None,
+        acceleration: Acceleration(-1),
+    }.create(&mut world);
+    world.write_storage::<Mass>().remove(entity);
+    let mut drag_events = world.write_storage::<Drag>().register_reader();
+
+    let events = Physics {
+        mass: Mass(11),
+        drag: Some(Drag(2)),
+        acceleration: Acceleration(-2),
+    }.update_with_events(&mut world, entity)?;
+    assert_eq!(events, vec![
+        ("mass", EventKind::Inserted),
+        ("drag", EventKind::Inserted),
+        ("acceleration", EventKind::Modified),
+    ]);
+
+    let events = Physics {
+        mass: Mass(12),
+        drag: None,
+        acceleration: Acceleration(-3),
+    }.update_with_events(&mut world, entity)?;
+    assert_eq!(events, vec![
+        ("mass", EventKind::Modified),
+        ("drag", EventKind::Removed),
+        ("acceleration", EventKind::Modified),
+    ]);
+
+    // The reported events should match the events emitted by the flagged storage
+    let storage = world.read_storage::<Drag>();
+    let emitted: Vec<_> = storage.channel().read(&mut drag_events).cloned().collect();
+    assert_eq!(emitted, vec![ComponentEvent::Inserted(entity.id()), ComponentEvent::Removed(entity.id())]);
+    drop(storage);
+
+    // Nothing is written for a field that stays absent
+    let events = Physics {
+        mass: Mass(12),
+        drag: None,
+        acceleration: Acceleration(-3),
+    }.update_with_events(&mut world, entity)?;
+    assert_eq!(events, vec![("mass", EventKind::Modified), ("acceleration", EventKind::Modified)]);
+
+    Ok(())
+}
+
+#[test]
+fn fields_processed_in_declaration_order() -> Result<(), SpecsError> {
+    let mut world = physics_world();
+
+    let entity = Physics {
+        mass: Mass(10),
+        drag: Some(Drag(2)),
+        acceleration: Acceleration(-1),
+    }.create(&mut world);
+    assert_eq!(take_log(), vec![("Mass", "insert"), ("Drag", "insert"), ("Acceleration", "insert")]);
+
+    Physics {
+        mass: Mass(11),
+        drag: None,
+        acceleration: Acceleration(-2),
+    }.update(&mut world, entity)?;
+    assert_eq!(take_log(), vec![("Mass", "modify"), ("Drag", "remove"), ("Acceleration", "modify")]);
+
+    Physics {
+        mass: Mass(12),
+        drag: Some(Drag(3)),
+        acceleration: Acceleration(-3),
+    }.update(&mut world, entity)?;
+    assert_eq!(take_log(), vec![("Mass", "modify"), ("Drag", "insert"), ("Acceleration", "modify")]);
+
+    Ok(())
+}
+
+#[test]
+fn remove_skips_absent_optionals() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Body {
+        mass: Mass,
+        drag: Option<Drag>,
+        spin: Option<Spin>,
+        buoyancy: Option<Buoyancy>,
+    }
+
+    let mut world = physics_world();
+    let entity = Body {
+        mass: Mass(10),
+        drag: None,
+        spin: Some(Spin(3)),
+        buoyancy: None,
+    }.create(&mut world);
+    take_log();
+
+    let removed = Body::remove(&mut world, entity);
+    assert_eq!(removed, Body {
+        mass: Mass(10),
+        drag: None,
+        spin: Some(Spin(3)),
+        buoyancy: None,
+    });
+    // Only the components that were present are removed
+    assert_eq!(take_log(), vec![("Mass", "remove"), ("Spin", "remove")]);
+    assert_eq!(get(&world, entity), None::<Spin>);
+}
+
+#[test]
+fn remove_and_create_round_trip_order() {
+    let mut world = physics_world();
+    let mut mass_reader = world.write_storage::<Mass>().register_reader();
+    let mut drag_reader = world.write_storage::<Drag>().register_reader();
+
+    let physics = Physics {
+        mass: Mass(10),
+        drag: Some(Drag(2)),
+        acceleration: Acceleration(-1),
+    };
+    let entity = physics.clone().create(&mut world);
+    take_log();
+
+    let removed = Physics::remove(&mut world, entity);
+    assert_eq!(removed, physics);
+    assert_eq!(take_log(), vec![("Mass", "remove"), ("Drag", "remove"), ("Acceleration", "remove")]);
+
+    let entity2 = removed.create(&mut world);
+    assert_eq!(take_log(), vec![("Mass", "insert"), ("Drag", "insert"), ("Acceleration", "insert")]);
+
+    // The flagged storages report the same events for each component
+    let id = entity.id();
+    let id2 = entity2.id();
+    let expected = vec![ComponentEvent::Inserted(id), ComponentEvent::Removed(id), ComponentEvent::Inserted(id2)];
+    let mass_events: Vec<_> = world.read_storage::<Mass>().channel().read(&mut mass_reader).cloned().collect();
+    assert_eq!(mass_events, expected);
+    let drag_events: Vec<_> = world.read_storage::<Drag>().channel().read(&mut drag_reader).cloned().collect();
+    assert_eq!(drag_events, expected);
+}
+
+#[test]
+fn create_with_readers_observes_changes() -> Result<(), SpecsError> {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Motion {
+        mass: Mass,
+        spin: Option<Spin>,
+    }
+
+    let mut world = physics_world();
+    let (entity, mut readers) = Motion {mass: Mass(10), spin: None}.create_with_readers(&mut world);
+
+    Motion {mass: Mass(11), spin: Some(Spin(2))}.update(&mut world, entity)?;
+
+    let mass_events: Vec<_> = world.read_storage::<Mass>().channel().read(&mut readers.mass).cloned().collect();
+    assert_eq!(mass_events, vec![ComponentEvent::Modified(entity.id())]);
+    let spin_events: Vec<_> = world.read_storage::<Spin>().channel().read(&mut readers.spin).cloned().collect();
+    assert_eq!(spin_events, vec![ComponentEvent::Inserted(entity.id())]);
+    Ok(())
+}
+
+#[test]
+fn order_attribute_controls_insertion_order() -> Result<(), SpecsError> {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct OrderedPhysics {
+        #[component_group(order = 2)]
+        mass: Mass,
+        drag: Option<Drag>,
+        #[component_group(order = 0)]
+        acceleration: Acceleration,
+    }
+
+    let mut world = physics_world();
+    let physics = OrderedPhysics {
+        mass: Mass(10),
+        drag: Some(Drag(2)),
+        acceleration: Acceleration(-1),
+    };
+    let entity = physics.clone().create(&mut world);
+    assert_eq!(take_log(), vec![("Acceleration", "insert"), ("Drag", "insert"), ("Mass", "insert")]);
+
+    OrderedPhysics {drag: None, ..physics.clone()}.update(&mut world, entity)?;
+    assert_eq!(take_log(), vec![("Acceleration", "modify"), ("Drag", "remove"), ("Mass", "modify")]);
+
+    // Removing still uses the order that the fields are declared in
+    let removed = OrderedPhysics::remove(&mut world, entity);
+    assert_eq!(removed, OrderedPhysics {drag: None, ..physics});
+    assert_eq!(take_log(), vec![("Mass", "remove"), ("Acceleration", "remove")]);
+    Ok(())
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "test-support")]
+
+use component_group::{ComponentGroup, ReadableGroup};
+use component_group::test_support::assert_moved;
+
+use specs::{World, WorldExt, Builder, Entity, Component, VecStorage, HashMapStorage};
+use specs_derive::Component;
+
+#[derive(Debug, Clone, Component, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Position {x: i32, y: i32}
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Health(u32);
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct Animation {frame: usize}
+
+#[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+struct PlayerComponents {
+    position: Position,
+    health: Health,
+    animation: Option<Animation>,
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register::<Position>();
+    world.register::<Health>();
+    world.register::<Animation>();
+    world
+}
+
+#[test]
+fn player_components_move() {
+    let mut level1 = new_world();
+    let mut level2 = new_world();
+
+    let group = PlayerComponents {
+        position: Position {x: 12, y: 59},
+        health: Health(5),
+        animation: Some(Animation {frame: 2}),
+    };
+    let entity = group.clone().create(&mut level1);
+
+    let new_entity = assert_moved::<PlayerComponents>(&mut level1, &mut level2, entity);
+    assert_eq!(PlayerComponents::from_world(&level2, new_entity), group);
+}
+
+#[test]
+fn optional_group_moves() {
+    #[derive(ComponentGroup, Debug, Clone, PartialEq, Eq)]
+    struct Optionals {
+        animation: Option<Animation>,
+    }
+
+    let mut level1 = new_world();
+    let mut level2 = new_world();
+
+    let entity = Optionals {animation: Some(Animation {frame: 2})}.create(&mut level1);
+    let new_entity = assert_moved::<Optionals>(&mut level1, &mut level2, entity);
+    assert_eq!(Optionals::from_world(&level2, new_entity).animation, Some(Animation {frame: 2}));
+}
+
+#[test]
+#[should_panic(expected = "the [\"Animation\"] components of the group are still in the source world")]
+fn component_left_behind() {
+    // A hand-written group whose remove method forgets about the animation
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Forgetful {
+        position: Position,
+        animation: Option<Animation>,
+    }
+
+    impl ReadableGroup for Forgetful {
+        fn read_first_from_world(_world: &World) -> Option<(Entity, Self)> {
+            unimplemented!()
+        }
+
+        fn read_from_world(world: &World, entity: Entity) -> Self {
+            Self {
+                position: world.read_storage::<Position>().get(entity).cloned().unwrap(),
+                animation: world.read_storage::<Animation>().get(entity).cloned(),
+            }
+        }
+    }
+
+    impl ComponentGroup for Forgetful {
+        type UpdateError = specs::error::Error;
+
+        fn create(self, world: &mut World) -> Entity {
+            let builder = world.create_entity().with(self.position);
+            match self.animation {
+                Some(animation) => builder.with(animation).build(),
+                None => builder.build(),
+            }
+        }
+
+        fn update(self, world: &mut World, entity: Entity) -> Result<(), Self::UpdateError> {
+            world.write_storage::<Position>().insert(entity, self.position)?;
+            if let Some(animation) = self.animation {
+                world.write_storage::<Animation>().insert(entity, animation)?;
+            }
+            Ok(())
+        }
+
+        fn remove(world: &mut World, entity: Entity) -> Self {
+            Self {
+                position: world.write_storage::<Position>().remove(entity).unwrap(),
+                animation: world.read_storage::<Animation>().get(entity).cloned(),
+            }
+        }
+
+        fn component_names_present(world: &World, entity: Entity) -> Vec<&'static str> {
+            let mut names = Vec::new();
+            if world.read_storage::<Position>().contains(entity) { names.push("Position"); }
+            if world.read_storage::<Animation>().contains(entity) { names.push("Animation"); }
+            names
+        }
+    }
+
+    let mut level1 = new_world();
+    let mut level2 = new_world();
+
+    let entity = Forgetful {
+        position: Position {x: 12, y: 59},
+        animation: Some(Animation {frame: 2}),
+    }.create(&mut level1);
+    assert_moved::<Forgetful>(&mut level1, &mut level2, entity);
+}